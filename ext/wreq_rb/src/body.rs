@@ -0,0 +1,365 @@
+use bytes::{Bytes, BytesMut};
+use http_body_util::BodyExt;
+use magnus::{exception, Error as MagnusError, RString, Ruby};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::Instant;
+use wreq::Response as WreqResponse;
+
+use crate::decompress::{DecodeError, Decoder};
+use crate::error::{ErrorKind, RequestError};
+use crate::limits::BodyLimits;
+use crate::{get_runtime, gvl};
+
+/// Chunk size `readpartial` uses when called without a size, same as http.rb.
+const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// A response body still sitting on the connection.
+struct BodyStream {
+    // None once the body has been read to the end
+    response: Option<WreqResponse>,
+    reader: ChunkReader,
+    // Part of the last chunk that didn't fit in the caller's readpartial size
+    pending: Bytes,
+    // Set once readpartial/each has handed out data
+    consumed: bool,
+    // Whole body, memoized by to_s
+    contents: Option<Bytes>,
+    method: String,
+    url: String,
+    started_at: Instant,
+    // When the last byte came off the connection
+    finished_at: Option<Instant>,
+}
+
+/// `Wreq::HTTP::Response::Body`, returned by `Response#body` for `stream: true`
+/// requests. Mirrors http.rb's `HTTP::Response::Body`: `readpartial`, `each` and
+/// `to_s`. Each chunk is awaited with the GVL released.
+#[magnus::wrap(class = "Wreq::HTTP::Response::Body")]
+#[derive(Clone)]
+pub struct RbBody {
+    stream: Arc<Mutex<BodyStream>>,
+}
+
+impl RbBody {
+    pub fn new(
+        response: WreqResponse,
+        limits: BodyLimits,
+        method: String,
+        url: String,
+        started_at: Instant,
+    ) -> Result<Self, RequestError> {
+        let reader = ChunkReader::new(&response, limits)?;
+
+        Ok(Self {
+            stream: Arc::new(Mutex::new(BodyStream {
+                response: Some(response),
+                reader,
+                pending: Bytes::new(),
+                consumed: false,
+                contents: None,
+                method,
+                url,
+                started_at,
+                finished_at: None,
+            })),
+        })
+    }
+
+    /// Returns the next chunk of at most `size` bytes. Raises EOFError once the
+    /// body is exhausted.
+    pub fn readpartial(&self, args: &[magnus::Value]) -> Result<RString, MagnusError> {
+        let size = match args.first() {
+            Some(size) => magnus::TryConvert::try_convert(*size)?,
+            None => DEFAULT_CHUNK_SIZE,
+        };
+        if size == 0 {
+            return Ok(RString::from_slice(&[]));
+        }
+
+        let mut stream = self.lock()?;
+        if stream.pending.is_empty() {
+            match stream.next_chunk()? {
+                Some(chunk) => stream.pending = chunk,
+                None => return Err(MagnusError::new(exception::eof_error(), "end of file reached")),
+            }
+        }
+
+        stream.consumed = true;
+        let len = size.min(stream.pending.len());
+        let chunk = stream.pending.split_to(len);
+        Ok(RString::from_slice(&chunk))
+    }
+
+    /// Reads the rest of the body and returns it as an ASCII-8BIT string.
+    pub fn to_s(&self) -> Result<RString, MagnusError> {
+        Ok(RString::from_slice(&self.contents()?))
+    }
+
+    /// The whole body. Raises `Wreq::HTTP::StateError` if part of it has already
+    /// been handed out by `readpartial`/`each`.
+    pub fn contents(&self) -> Result<Bytes, MagnusError> {
+        let mut stream = self.lock()?;
+        if let Some(contents) = &stream.contents {
+            return Ok(contents.clone());
+        }
+        if stream.consumed {
+            return Err(RequestError::new(
+                ErrorKind::State,
+                "body has already been consumed by readpartial/each",
+            )
+            .into_magnus());
+        }
+
+        let mut contents = BytesMut::new();
+        while let Some(chunk) = stream.next_chunk()? {
+            contents.extend_from_slice(&chunk);
+        }
+        let contents = contents.freeze();
+        stream.contents = Some(contents.clone());
+        Ok(contents)
+    }
+
+    /// Writes the rest of the body to `path`, decompressing as it goes. The GVL is
+    /// released for the whole copy. Returns the number of bytes written.
+    pub fn save_to(&self, path: &Path) -> Result<u64, MagnusError> {
+        let mut guard = self.lock()?;
+        let stream = &mut *guard;
+        if let Some(contents) = &stream.contents {
+            return save_bytes(path, contents);
+        }
+        if stream.consumed {
+            return Err(RequestError::new(
+                ErrorKind::State,
+                "body has already been consumed by readpartial/each",
+            )
+            .into_magnus());
+        }
+        stream.consumed = true;
+
+        let Some(response) = stream.response.as_mut() else {
+            return save_bytes(path, &[]);
+        };
+        let runtime = get_runtime()?;
+        let result = gvl::block_on_interruptible(&runtime, copy_body(response, &mut stream.reader, path));
+        stream.response = None;
+
+        let error = match result {
+            Some(Ok(written)) => {
+                stream.finished_at = Some(Instant::now());
+                return Ok(written);
+            }
+            Some(Err(SaveError::Io(e))) => io_error(path, e),
+            Some(Err(SaveError::Request(e))) => e
+                .with_request(&stream.method, &stream.url, stream.started_at.elapsed())
+                .into_magnus(),
+            None => {
+                let _ = std::fs::remove_file(path);
+                Ruby::get().expect("GVL re-acquired").thread_check_ints()?;
+                RequestError::new(ErrorKind::Interrupted, "HTTP body read interrupted").into_magnus()
+            }
+        };
+        // Don't leave a truncated file behind
+        let _ = std::fs::remove_file(path);
+        Err(error)
+    }
+
+    /// `None` until the body has been read to the end, or while another thread is
+    /// reading it.
+    pub fn finished_at(&self) -> Option<Instant> {
+        self.stream.try_lock().ok().and_then(|stream| stream.finished_at)
+    }
+
+    /// Never blocks: another thread holding the lock may have released the GVL,
+    /// and waiting here with the GVL held would deadlock both.
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, BodyStream>, MagnusError> {
+        match self.stream.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Ok(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => Err(RequestError::new(
+                ErrorKind::State,
+                "body is being read by another thread",
+            )
+            .into_magnus()),
+        }
+    }
+}
+
+impl BodyStream {
+    /// Pulls the next non-empty chunk off the connection with the GVL released.
+    /// Returns `None` at the end of the body.
+    fn next_chunk(&mut self) -> Result<Option<Bytes>, MagnusError> {
+        let Some(response) = self.response.as_mut() else {
+            return Ok(None);
+        };
+
+        let runtime = get_runtime()?;
+        let result = gvl::block_on_interruptible(&runtime, self.reader.next(response));
+
+        let result = match result {
+            Some(result) => result,
+            None => {
+                Ruby::get().expect("GVL re-acquired").thread_check_ints()?;
+                Err(RequestError::new(ErrorKind::Interrupted, "HTTP body read interrupted"))
+            }
+        };
+
+        match result {
+            Ok(Some(chunk)) => Ok(Some(chunk)),
+            Ok(None) => {
+                // Release the connection as soon as the body is drained
+                self.response = None;
+                self.finished_at = Some(Instant::now());
+                Ok(None)
+            }
+            Err(e) => {
+                self.response = None;
+                Err(e
+                    .with_request(&self.method, &self.url, self.started_at.elapsed())
+                    .into_magnus())
+            }
+        }
+    }
+}
+
+enum SaveError {
+    Request(RequestError),
+    Io(io::Error),
+}
+
+impl From<RequestError> for SaveError {
+    fn from(err: RequestError) -> Self {
+        SaveError::Request(err)
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+async fn copy_body(
+    response: &mut WreqResponse,
+    reader: &mut ChunkReader,
+    path: &Path,
+) -> Result<u64, SaveError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut written = 0;
+    while let Some(chunk) = reader.next(response).await? {
+        file.write_all(&chunk)?;
+        written += chunk.len() as u64;
+    }
+    file.flush()?;
+    Ok(written)
+}
+
+/// Writes an already buffered body to `path` with the GVL released.
+pub(crate) fn save_bytes(path: &Path, body: &[u8]) -> Result<u64, MagnusError> {
+    let runtime = get_runtime()?;
+    let result = gvl::block_on_interruptible(&runtime, async { std::fs::write(path, body) });
+    match result {
+        Some(Ok(())) => Ok(body.len() as u64),
+        Some(Err(e)) => Err(io_error(path, e)),
+        None => {
+            Ruby::get().expect("GVL re-acquired").thread_check_ints()?;
+            Err(RequestError::new(ErrorKind::Interrupted, "file write interrupted").into_magnus())
+        }
+    }
+}
+
+pub(crate) fn io_error(path: &Path, err: io::Error) -> MagnusError {
+    MagnusError::new(exception::io_error(), format!("{}: {}", path.display(), err))
+}
+
+/// Reads a body frame by frame, decoding Content-Encoding and enforcing the
+/// size limits as bytes arrive. Shared by buffered and streamed responses.
+pub(crate) struct ChunkReader {
+    // Taken when the body ends
+    decoder: Option<Decoder>,
+    limits: BodyLimits,
+    // Bytes received from the wire, before decompression
+    wire_bytes: u64,
+}
+
+impl ChunkReader {
+    /// Fails straight away when the announced Content-Length is over the limit.
+    pub(crate) fn new(response: &WreqResponse, limits: BodyLimits) -> Result<Self, RequestError> {
+        if let Some(content_length) = response.content_length() {
+            limits.check_body_size(content_length)?;
+        }
+
+        let decoder = response
+            .headers()
+            .get("content-encoding")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|s| !s.is_empty() && !s.eq_ignore_ascii_case("identity"))
+            .map(Decoder::new)
+            .transpose()
+            .map_err(RequestError::decompression)?;
+
+        Ok(Self {
+            decoder,
+            limits,
+            wire_bytes: 0,
+        })
+    }
+
+    pub(crate) fn wire_bytes(&self) -> u64 {
+        self.wire_bytes
+    }
+
+    /// Reads and decodes frames until one yields data. Returns `None` at the end
+    /// of the body, after the decoder's final output.
+    pub(crate) async fn next(&mut self, response: &mut WreqResponse) -> Result<Option<Bytes>, RequestError> {
+        loop {
+            let Some(chunk) = read_frame(response).await? else {
+                // End of body: flush whatever the decoder still holds
+                let Some(decoder) = self.decoder.take() else {
+                    return Ok(None);
+                };
+                let tail = decoder.finish().map_err(|e| self.decode_error(e))?;
+                return Ok(Some(Bytes::from(tail)).filter(|tail| !tail.is_empty()));
+            };
+
+            self.wire_bytes += chunk.len() as u64;
+            self.limits.check_body_size(self.wire_bytes)?;
+
+            let limit = self.limits.decompressed_limit(self.wire_bytes);
+            let chunk = match self.decoder.as_mut() {
+                Some(decoder) => match decoder.decode(&chunk, limit) {
+                    Ok(decoded) => Bytes::from(decoded),
+                    Err(e) => return Err(self.decode_error(e)),
+                },
+                None => chunk,
+            };
+            // A compressed chunk may not decode to anything yet
+            if !chunk.is_empty() {
+                return Ok(Some(chunk));
+            }
+        }
+    }
+
+    fn decode_error(&self, err: DecodeError) -> RequestError {
+        match err {
+            DecodeError::LimitExceeded => self.limits.decompressed_error(self.wire_bytes),
+            err => RequestError::decompression(err),
+        }
+    }
+}
+
+/// Next data frame of the body, skipping trailers and empty frames.
+pub(crate) async fn read_frame(response: &mut WreqResponse) -> Result<Option<Bytes>, RequestError> {
+    while let Some(frame) = response.frame().await {
+        let frame = frame.map_err(|e| RequestError::from_wreq(&e, "Failed to read response body"))?;
+        if let Ok(data) = frame.into_data() {
+            if !data.is_empty() {
+                return Ok(Some(data));
+            }
+        }
+    }
+    Ok(None)
+}
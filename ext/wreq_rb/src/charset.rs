@@ -0,0 +1,133 @@
+use encoding_rs::{Encoding, UTF_8};
+
+/// How far into the body to look for a `<meta>` charset declaration. The HTML
+/// spec requires it within the first 1024 bytes.
+const META_SNIFF_LIMIT: usize = 1024;
+
+/// Decodes a response body to UTF-8.
+///
+/// The charset is picked from, in order: the client's `encoding()`, the
+/// Content-Type charset, a byte order mark, an HTML `<meta>` declaration, and
+/// finally UTF-8. Labels that aren't recognised are skipped.
+pub fn decode(body: &[u8], explicit: Option<&str>, content_type: Option<&str>) -> String {
+    let (text, _) = detect(body, explicit, content_type).decode_with_bom_removal(body);
+    text.into_owned()
+}
+
+/// Returns the encoding `decode` would use for this body.
+pub fn detect(body: &[u8], explicit: Option<&str>, content_type: Option<&str>) -> &'static Encoding {
+    explicit
+        .and_then(lookup)
+        .or_else(|| content_type.and_then(charset_param).and_then(lookup))
+        .or_else(|| Encoding::for_bom(body).map(|(encoding, _)| encoding))
+        .or_else(|| meta_charset(body).and_then(lookup))
+        .unwrap_or(UTF_8)
+}
+
+fn lookup(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().trim_matches(|c| c == '"' || c == '\'').as_bytes())
+}
+
+/// `text/html; charset="Shift_JIS"` -> `Shift_JIS`
+pub fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|part| {
+        let (name, value) = part.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("charset") {
+            let value = value.trim().trim_matches('"');
+            (!value.is_empty()).then_some(value)
+        } else {
+            None
+        }
+    })
+}
+
+/// Finds `<meta charset="...">` or `<meta http-equiv="Content-Type" content="...; charset=...">`
+/// near the top of an HTML document.
+fn meta_charset(body: &[u8]) -> Option<&str> {
+    let head = &body[..body.len().min(META_SNIFF_LIMIT)];
+    let lower = head.to_ascii_lowercase();
+
+    let mut offset = 0;
+    while let Some(found) = find(&lower[offset..], b"<meta") {
+        let start = offset + found;
+        let end = lower[start..].iter().position(|&b| b == b'>').map_or(lower.len(), |p| start + p);
+        if let Some(pos) = find(&lower[start..end], b"charset=") {
+            let value = &head[start + pos + b"charset=".len()..end];
+            let value = &value[value.iter().take_while(|&&b| b == b'"' || b == b'\'').count()..];
+            let len = value
+                .iter()
+                .take_while(|&&b| !matches!(b, b'"' | b'\'' | b';' | b'/' | b' ' | b'>'))
+                .count();
+            if let Ok(label) = std::str::from_utf8(&value[..len]) {
+                if !label.is_empty() {
+                    return Some(label);
+                }
+            }
+        }
+        offset = end;
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_to_utf8() {
+        assert_eq!(detect(b"plain", None, None), UTF_8);
+        assert_eq!(decode("héllo".as_bytes(), None, Some("text/plain")), "héllo");
+    }
+
+    #[test]
+    fn test_content_type_charset() {
+        // "こんにちは" in Shift_JIS
+        let body = [0x82, 0xb1, 0x82, 0xf1, 0x82, 0xc9, 0x82, 0xbf, 0x82, 0xcd];
+        assert_eq!(decode(&body, None, Some("text/html; charset=Shift_JIS")), "こんにちは");
+    }
+
+    #[test]
+    fn test_explicit_encoding_wins_over_content_type() {
+        // "Привет" in windows-1251
+        let body = [0xcf, 0xf0, 0xe8, 0xe2, 0xe5, 0xf2];
+        let text = decode(&body, Some("windows-1251"), Some("text/html; charset=utf-8"));
+        assert_eq!(text, "Привет");
+    }
+
+    #[test]
+    fn test_unknown_label_falls_through() {
+        let body = [0x63, 0x61, 0x66, 0xe9];
+        assert_eq!(decode(&body, Some("no-such-charset"), Some("text/plain; charset=ISO-8859-1")), "café");
+    }
+
+    #[test]
+    fn test_bom_detection() {
+        let body = [0xff, 0xfe, b'h', 0x00, b'i', 0x00];
+        assert_eq!(detect(&body, None, None).name(), "UTF-16LE");
+        assert_eq!(decode(&body, None, None), "hi");
+    }
+
+    #[test]
+    fn test_meta_charset() {
+        let html = b"<html><head><meta charset=\"gbk\"></head><body>\xc4\xe3\xba\xc3</body></html>";
+        assert_eq!(decode(html, None, Some("text/html")), "<html><head><meta charset=\"gbk\"></head><body>你好</body></html>");
+    }
+
+    #[test]
+    fn test_meta_http_equiv_charset() {
+        let html = b"<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=windows-1251\">";
+        assert_eq!(meta_charset(html), Some("windows-1251"));
+    }
+
+    #[test]
+    fn test_charset_param() {
+        assert_eq!(charset_param("text/html; charset=\"UTF-8\""), Some("UTF-8"));
+        assert_eq!(charset_param("text/html;Charset=gbk"), Some("gbk"));
+        assert_eq!(charset_param("text/html"), None);
+        assert_eq!(charset_param("text/html; charset="), None);
+    }
+}
@@ -0,0 +1,222 @@
+use brotli::CompressorWriter;
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use http_body::{Body, Frame, SizeHint};
+use magnus::{exception, Error as MagnusError, Symbol, TryConvert, Value};
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Brotli settings: a middle quality keeps compression fast enough to run
+/// inline with the upload.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Content-Encoding applied to request bodies with `compress:`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Brotli,
+}
+
+impl Compression {
+    /// `:gzip`, `:zstd` or `:br`; `nil` and `false` turn compression off.
+    pub fn from_value(value: Value) -> Result<Option<Self>, MagnusError> {
+        if !value.to_bool() {
+            return Ok(None);
+        }
+        match &*Symbol::try_convert(value)?.name()? {
+            "gzip" => Ok(Some(Compression::Gzip)),
+            "zstd" => Ok(Some(Compression::Zstd)),
+            "br" => Ok(Some(Compression::Brotli)),
+            name => Err(MagnusError::new(
+                exception::arg_error(),
+                format!("unknown compression: :{} (expected :gzip, :zstd or :br)", name),
+            )),
+        }
+    }
+
+    pub fn content_encoding(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Brotli => "br",
+        }
+    }
+
+    /// Compresses a body that is already in memory.
+    pub fn compress(self, data: &[u8]) -> io::Result<Bytes> {
+        let mut encoder = Encoder::new(self)?;
+        let head = encoder.write(data)?;
+        let tail = encoder.finish()?;
+        let mut output = Vec::with_capacity(head.len() + tail.len());
+        output.extend_from_slice(&head);
+        output.extend_from_slice(&tail);
+        Ok(Bytes::from(output))
+    }
+
+    /// Compresses a streamed body as it is sent. The compressed length isn't
+    /// known up front, so it goes out chunked.
+    pub fn wrap<B>(self, body: B) -> io::Result<CompressedBody<B>> {
+        Ok(CompressedBody {
+            inner: body,
+            encoder: Some(Encoder::new(self)?),
+        })
+    }
+}
+
+/// Incremental encoder writing into a buffer that is drained after each write.
+enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Brotli(Box<CompressorWriter<Vec<u8>>>),
+}
+
+impl Encoder {
+    fn new(compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(Vec::new(), flate2::Compression::default())),
+            Compression::Zstd => Encoder::Zstd(zstd::stream::write::Encoder::new(
+                Vec::new(),
+                zstd::DEFAULT_COMPRESSION_LEVEL,
+            )?),
+            Compression::Brotli => Encoder::Brotli(Box::new(CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW,
+            ))),
+        })
+    }
+
+    /// Feeds `data` in and returns whatever compressed output is ready, which
+    /// may be nothing yet.
+    fn write(&mut self, data: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            Encoder::Zstd(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            Encoder::Brotli(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(output)))
+    }
+
+    /// Ends the stream and returns the rest of the output.
+    fn finish(self) -> io::Result<Bytes> {
+        let output = match self {
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            Encoder::Brotli(encoder) => encoder.into_inner(),
+        };
+        Ok(Bytes::from(output))
+    }
+}
+
+/// A request body compressed frame by frame.
+pub struct CompressedBody<B> {
+    inner: B,
+    // Taken once the inner body has ended
+    encoder: Option<Encoder>,
+}
+
+impl<B> Body for CompressedBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        let this = &mut *self;
+        loop {
+            let Some(encoder) = this.encoder.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => {
+                    let Ok(data) = frame.into_data() else {
+                        // Trailers can't be sent once compressed
+                        continue;
+                    };
+                    let compressed = encoder.write(&data)?;
+                    if !compressed.is_empty() {
+                        return Poll::Ready(Some(Ok(Frame::data(compressed))));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => {
+                    let tail = this.encoder.take().map_or(Ok(Bytes::new()), Encoder::finish)?;
+                    if !tail.is_empty() {
+                        return Poll::Ready(Some(Ok(Frame::data(tail))));
+                    }
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompress::Decoder;
+    use http_body_util::{BodyExt, StreamBody};
+
+    const TEXT: &[u8] = b"the quick brown fox jumps over the lazy dog, again and again and again";
+
+    fn roundtrip(compression: Compression, compressed: &[u8]) -> Vec<u8> {
+        let mut decoder = Decoder::new(compression.content_encoding()).unwrap();
+        let mut output = decoder.decode(compressed, None).unwrap();
+        output.extend(decoder.finish().unwrap());
+        output
+    }
+
+    #[test]
+    fn test_compress_roundtrips() {
+        for compression in [Compression::Gzip, Compression::Zstd, Compression::Brotli] {
+            let compressed = compression.compress(TEXT).unwrap();
+            assert_eq!(roundtrip(compression, &compressed), TEXT, "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_streamed_body_roundtrips() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        for compression in [Compression::Gzip, Compression::Zstd, Compression::Brotli] {
+            let chunks = TEXT
+                .chunks(10)
+                .map(|chunk| Ok::<_, io::Error>(Frame::data(Bytes::copy_from_slice(chunk))));
+            let body = compression.wrap(StreamBody::new(futures::stream::iter(chunks))).unwrap();
+            assert_eq!(body.size_hint().exact(), None);
+
+            let compressed = runtime.block_on(body.collect()).unwrap().to_bytes();
+            assert_eq!(roundtrip(compression, &compressed), TEXT, "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_content_encoding() {
+        assert_eq!(Compression::Gzip.content_encoding(), "gzip");
+        assert_eq!(Compression::Zstd.content_encoding(), "zstd");
+        assert_eq!(Compression::Brotli.content_encoding(), "br");
+    }
+}
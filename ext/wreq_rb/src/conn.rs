@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use wreq::Response as WreqResponse;
+use wreq::Version;
+
+/// Connections remembered per client before the set is reset. A client cycling
+/// through this many connections only forgets which ones it has seen, nothing else.
+const MAX_TRACKED_CONNECTIONS: usize = 4096;
+
+/// Remembers the connections a client has handed out a response on.
+///
+/// wreq doesn't say whether a response came over a pooled connection, so this is
+/// a heuristic: a TCP connection is identified by its local/remote address pair,
+/// and seeing the same pair again on the same client usually means the pool
+/// handed it back. It can be wrong when the OS recycles a local port for a new
+/// connection to the same peer, or when a proxy hides the real connections.
+#[derive(Clone, Default)]
+pub struct ConnectionTracker {
+    seen: Arc<Mutex<HashSet<(SocketAddr, SocketAddr)>>>,
+}
+
+impl ConnectionTracker {
+    /// Records the connection and returns whether its address pair had been seen before.
+    pub fn observe(&self, local: Option<SocketAddr>, remote: Option<SocketAddr>) -> bool {
+        let (Some(local), Some(remote)) = (local, remote) else {
+            return false;
+        };
+        let mut seen = self.seen.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if seen.contains(&(local, remote)) {
+            return true;
+        }
+        if seen.len() >= MAX_TRACKED_CONNECTIONS {
+            seen.clear();
+        }
+        seen.insert((local, remote));
+        false
+    }
+}
+
+/// What the response says about the connection it arrived on, captured before
+/// the body is read.
+#[derive(Clone, Copy, Debug)]
+pub struct ConnectionInfo {
+    pub version: Version,
+    pub local_addr: Option<SocketAddr>,
+    pub remote_addr: Option<SocketAddr>,
+    // Guessed by `ConnectionTracker`, not reported by wreq
+    pub seen_before: bool,
+}
+
+impl ConnectionInfo {
+    pub fn new(response: &WreqResponse, tracker: &ConnectionTracker) -> Self {
+        let local_addr = response.local_addr();
+        let remote_addr = response.remote_addr();
+        Self {
+            version: response.version(),
+            local_addr,
+            remote_addr,
+            seen_before: tracker.observe(local_addr, remote_addr),
+        }
+    }
+
+    /// `"1.1"`, `"2"`: the version number as http.rb's `Response#version` gives it.
+    pub fn version_str(&self) -> &'static str {
+        version_str(self.version)
+    }
+}
+
+fn version_str(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_11 => "1.1",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Option<SocketAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_same_connection_is_seen_before() {
+        let tracker = ConnectionTracker::default();
+        assert!(!tracker.observe(addr("10.0.0.1:50000"), addr("93.184.216.34:443")));
+        assert!(tracker.observe(addr("10.0.0.1:50000"), addr("93.184.216.34:443")));
+    }
+
+    #[test]
+    fn test_new_local_port_is_a_new_connection() {
+        let tracker = ConnectionTracker::default();
+        tracker.observe(addr("10.0.0.1:50000"), addr("93.184.216.34:443"));
+        assert!(!tracker.observe(addr("10.0.0.1:50001"), addr("93.184.216.34:443")));
+    }
+
+    #[test]
+    fn test_clones_share_connections() {
+        let tracker = ConnectionTracker::default();
+        tracker.observe(addr("10.0.0.1:50000"), addr("93.184.216.34:443"));
+        assert!(tracker.clone().observe(addr("10.0.0.1:50000"), addr("93.184.216.34:443")));
+    }
+
+    #[test]
+    fn test_unknown_addresses_are_never_seen_before() {
+        let tracker = ConnectionTracker::default();
+        assert!(!tracker.observe(None, addr("93.184.216.34:443")));
+        assert!(!tracker.observe(None, addr("93.184.216.34:443")));
+    }
+
+    #[test]
+    fn test_version_str() {
+        assert_eq!(version_str(Version::HTTP_11), "1.1");
+        assert_eq!(version_str(Version::HTTP_2), "2");
+        assert_eq!(version_str(Version::HTTP_10), "1.0");
+    }
+}
@@ -1,53 +1,329 @@
-use brotli::Decompressor;
-use flate2::read::{DeflateDecoder, GzDecoder};
-use std::io::Read;
-
-pub fn decompress(data: &[u8], encoding: &str) -> Result<Vec<u8>, String> {
-    // Handle multiple encodings (split on comma, reverse order)
-    if encoding.contains(',') {
-        let encodings: Vec<&str> = encoding.split(',').map(|s| s.trim()).collect();
-        let mut result = data.to_vec();
-        for enc in encodings.iter().rev() {
-            result = decompress_single(&result, enc)?;
-        }
-        return Ok(result);
-    }
-
-    decompress_single(data, encoding)
-}
-
-fn decompress_single(data: &[u8], encoding: &str) -> Result<Vec<u8>, String> {
-    let encoding_lower = encoding.trim().to_lowercase();
-
-    match encoding_lower.as_str() {
-        "gzip" | "x-gzip" => {
-            let mut decoder = GzDecoder::new(data);
-            let mut decompressed = Vec::new();
-            decoder
-                .read_to_end(&mut decompressed)
-                .map_err(|e| format!("gzip decompression error: {}", e))?;
-            Ok(decompressed)
-        }
-        "deflate" => {
-            let mut decoder = DeflateDecoder::new(data);
-            let mut decompressed = Vec::new();
-            decoder
-                .read_to_end(&mut decompressed)
-                .map_err(|e| format!("deflate decompression error: {}", e))?;
-            Ok(decompressed)
-        }
-        "br" => {
-            let mut decoder = Decompressor::new(data, 4096);
-            let mut decompressed = Vec::new();
-            decoder
-                .read_to_end(&mut decompressed)
-                .map_err(|e| format!("brotli decompression error: {}", e))?;
-            Ok(decompressed)
-        }
-        "zstd" => {
-            zstd::stream::decode_all(data).map_err(|e| format!("zstd decompression error: {}", e))
-        }
-        "identity" | "" => Ok(data.to_vec()),
-        _ => Err(format!("unsupported encoding: {}", encoding)),
+use brotli::DecompressorWriter;
+use flate2::write::{DeflateDecoder, GzDecoder};
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// Corrupt, truncated or unsupported encoding
+    Invalid(String),
+    /// Output would have grown past the limit passed to `decode`
+    LimitExceeded,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Invalid(message) => f.write_str(message),
+            DecodeError::LimitExceeded => f.write_str("decompressed size limit exceeded"),
+        }
+    }
+}
+
+/// Incremental decoder for a Content-Encoding value, fed the body one chunk at a
+/// time as it arrives. Only the decoder state and the output of the current chunk
+/// are held in memory.
+pub struct Decoder {
+    // In decoding order: the last encoding applied is undone first
+    layers: Vec<Layer>,
+}
+
+impl Decoder {
+    pub fn new(encoding: &str) -> Result<Self, DecodeError> {
+        // Handle multiple encodings (split on comma, reverse order)
+        let mut layers = Vec::new();
+        for enc in encoding.split(',').rev() {
+            if let Some(layer) = Layer::new(enc)? {
+                layers.push(layer);
+            }
+        }
+        Ok(Self { layers })
+    }
+
+    /// Decodes the next chunk of the body. May return an empty Vec while the
+    /// decoder is waiting for more input.
+    ///
+    /// `limit` caps the total output of every layer so far. Decoding stops as soon
+    /// as it would be exceeded, in the middle of the chunk, so a decompression bomb
+    /// never gets inflated in memory.
+    pub fn decode(&mut self, chunk: &[u8], limit: Option<u64>) -> Result<Vec<u8>, DecodeError> {
+        let mut output: Option<Vec<u8>> = None;
+        for layer in &mut self.layers {
+            layer.sink_mut().limit = limit;
+            let input = output.as_deref().unwrap_or(chunk);
+            output = Some(layer.write(input)?);
+        }
+        Ok(output.unwrap_or_else(|| chunk.to_vec()))
+    }
+
+    /// Flushes whatever the decoders still hold once the body has ended, and fails
+    /// if the body was truncated.
+    pub fn finish(self) -> Result<Vec<u8>, DecodeError> {
+        let mut tail = Vec::new();
+        for mut layer in self.layers {
+            let mut output = layer.write(&tail)?;
+            output.extend(layer.finish()?);
+            tail = output;
+        }
+        Ok(tail)
+    }
+}
+
+/// Where a layer writes its output. Refuses writes past `limit` total bytes.
+#[derive(Default)]
+struct Sink {
+    buf: Vec<u8>,
+    written: u64,
+    limit: Option<u64>,
+}
+
+/// Marker carried in the io::Error a full `Sink` returns.
+#[derive(Debug)]
+struct LimitExceeded;
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("decompressed size limit exceeded")
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl Write for Sink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.written + data.len() as u64;
+        if self.limit.is_some_and(|limit| written > limit) {
+            return Err(io::Error::other(LimitExceeded));
+        }
+        self.written = written;
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Layer {
+    Gzip(GzDecoder<Sink>),
+    Deflate(DeflateDecoder<Sink>),
+    Brotli(Box<DecompressorWriter<Sink>>),
+    // The raw writer, unlike `write::Decoder`, fails to finish an incomplete frame
+    Zstd(zstd::stream::zio::Writer<Sink, zstd::stream::raw::Decoder<'static>>),
+}
+
+impl Layer {
+    /// `None` for identity.
+    fn new(encoding: &str) -> Result<Option<Self>, DecodeError> {
+        let encoding_lower = encoding.trim().to_lowercase();
+
+        let layer = match encoding_lower.as_str() {
+            "gzip" | "x-gzip" => Layer::Gzip(GzDecoder::new(Sink::default())),
+            "deflate" => Layer::Deflate(DeflateDecoder::new(Sink::default())),
+            "br" => Layer::Brotli(Box::new(DecompressorWriter::new(Sink::default(), 4096))),
+            "zstd" => Layer::Zstd(zstd::stream::zio::Writer::new(
+                Sink::default(),
+                zstd::stream::raw::Decoder::new()
+                    .map_err(|e| DecodeError::Invalid(format!("zstd decompression error: {}", e)))?,
+            )),
+            "identity" | "" => return Ok(None),
+            _ => return Err(DecodeError::Invalid(format!("unsupported encoding: {}", encoding))),
+        };
+        Ok(Some(layer))
+    }
+
+    fn sink_mut(&mut self) -> &mut Sink {
+        match self {
+            Layer::Gzip(decoder) => decoder.get_mut(),
+            Layer::Deflate(decoder) => decoder.get_mut(),
+            Layer::Brotli(decoder) => decoder.get_mut(),
+            Layer::Zstd(decoder) => decoder.writer_mut(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Layer::Gzip(_) => "gzip",
+            Layer::Deflate(_) => "deflate",
+            Layer::Brotli(_) => "brotli",
+            Layer::Zstd(_) => "zstd",
+        }
+    }
+
+    /// Feeds `input` through and takes everything decoded so far.
+    fn write(&mut self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let result = match self {
+            Layer::Gzip(decoder) => decoder.write_all(input).and_then(|_| decoder.flush()),
+            Layer::Deflate(decoder) => decoder.write_all(input).and_then(|_| decoder.flush()),
+            Layer::Brotli(decoder) => decoder.write_all(input).and_then(|_| decoder.flush()),
+            Layer::Zstd(decoder) => decoder.write_all(input).and_then(|_| decoder.flush()),
+        };
+        result.map_err(|e| self.error(e))?;
+        Ok(std::mem::take(&mut self.sink_mut().buf))
+    }
+
+    fn finish(self) -> Result<Vec<u8>, DecodeError> {
+        let name = self.name();
+        let result = match self {
+            Layer::Gzip(decoder) => decoder.finish(),
+            Layer::Deflate(decoder) => decoder.finish(),
+            Layer::Brotli(mut decoder) => decoder
+                .close()
+                .map(|_| std::mem::take(decoder.get_mut())),
+            Layer::Zstd(mut decoder) => decoder.finish().map(|_| decoder.into_inner().0),
+        };
+        result
+            .map(|sink| sink.buf)
+            .map_err(|e| layer_error(name, e))
+    }
+
+    fn error(&self, err: io::Error) -> DecodeError {
+        layer_error(self.name(), err)
+    }
+}
+
+fn layer_error(name: &str, err: io::Error) -> DecodeError {
+    if err.get_ref().is_some_and(|inner| inner.is::<LimitExceeded>()) {
+        DecodeError::LimitExceeded
+    } else {
+        DecodeError::Invalid(format!("{} decompression error: {}", name, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::{DeflateEncoder, GzEncoder};
+
+    const TEXT: &[u8] = b"The quick brown fox jumps over the lazy dog. ";
+
+    fn sample() -> Vec<u8> {
+        TEXT.repeat(500)
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        {
+            let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+            encoder.write_all(data).unwrap();
+        }
+        output
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        zstd::stream::encode_all(data, 3).unwrap()
+    }
+
+    /// Decodes `compressed` in `chunk_size` pieces, as the body would arrive.
+    fn decode_in_chunks(compressed: &[u8], encoding: &str, chunk_size: usize) -> Vec<u8> {
+        let mut decoder = Decoder::new(encoding).unwrap();
+        let mut output = Vec::new();
+        for chunk in compressed.chunks(chunk_size) {
+            output.extend(decoder.decode(chunk, None).unwrap());
+        }
+        output.extend(decoder.finish().unwrap());
+        output
+    }
+
+    #[test]
+    fn test_streaming_gzip() {
+        assert_eq!(decode_in_chunks(&gzip(&sample()), "gzip", 7), sample());
+    }
+
+    #[test]
+    fn test_streaming_deflate() {
+        assert_eq!(decode_in_chunks(&deflate(&sample()), "deflate", 7), sample());
+    }
+
+    #[test]
+    fn test_streaming_brotli() {
+        assert_eq!(decode_in_chunks(&brotli(&sample()), "br", 7), sample());
+    }
+
+    #[test]
+    fn test_streaming_zstd() {
+        assert_eq!(decode_in_chunks(&zstd(&sample()), "zstd", 7), sample());
+    }
+
+    #[test]
+    fn test_streaming_chained_encodings() {
+        // Content-Encoding: gzip, br => gzip applied first, then brotli
+        let compressed = brotli(&gzip(&sample()));
+        assert_eq!(decode_in_chunks(&compressed, "gzip, br", 13), sample());
+    }
+
+    #[test]
+    fn test_whole_body_in_one_chunk() {
+        assert_eq!(decode_in_chunks(&gzip(&sample()), "GZIP", usize::MAX), sample());
+        assert_eq!(decode_in_chunks(b"plain", "identity", usize::MAX), b"plain");
+    }
+
+    #[test]
+    fn test_limit_stops_decoding_mid_chunk() {
+        let bomb = zstd(&vec![0u8; 10 * 1024 * 1024]);
+        let mut decoder = Decoder::new("zstd").unwrap();
+        assert_eq!(decoder.decode(&bomb, Some(64 * 1024)), Err(DecodeError::LimitExceeded));
+    }
+
+    #[test]
+    fn test_limit_applies_across_chunks() {
+        let compressed = gzip(&sample());
+        let mut decoder = Decoder::new("gzip").unwrap();
+        let limit = Some(sample().len() as u64 - 1);
+        let result = compressed
+            .chunks(64)
+            .try_for_each(|chunk| decoder.decode(chunk, limit).map(drop));
+        let result = result.and_then(|_| decoder.finish().map(drop));
+        assert_eq!(result, Err(DecodeError::LimitExceeded));
+    }
+
+    #[test]
+    fn test_limit_allows_exact_size() {
+        let mut decoder = Decoder::new("br").unwrap();
+        let mut output = decoder.decode(&brotli(&sample()), Some(sample().len() as u64)).unwrap();
+        output.extend(decoder.finish().unwrap());
+        assert_eq!(output, sample());
+    }
+
+    #[test]
+    fn test_truncated_gzip_fails_on_finish() {
+        let compressed = gzip(&sample());
+        let mut decoder = Decoder::new("gzip").unwrap();
+        decoder.decode(&compressed[..compressed.len() / 2], None).unwrap();
+        assert!(decoder.finish().is_err());
+    }
+
+    #[test]
+    fn test_truncated_zstd_fails_on_finish() {
+        let compressed = zstd(&sample());
+        let mut decoder = Decoder::new("zstd").unwrap();
+        decoder.decode(&compressed[..compressed.len() / 2], None).unwrap();
+        assert!(decoder.finish().is_err());
+    }
+
+    #[test]
+    fn test_concatenated_zstd_frames() {
+        let mut compressed = zstd(TEXT);
+        compressed.extend(zstd(TEXT));
+        assert_eq!(decode_in_chunks(&compressed, "zstd", 5), TEXT.repeat(2));
+    }
+
+    #[test]
+    fn test_unsupported_encoding() {
+        assert!(Decoder::new("compress").is_err());
     }
 }
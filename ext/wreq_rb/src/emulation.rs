@@ -0,0 +1,309 @@
+use magnus::r_hash::ForEach;
+use magnus::{exception, Error as MagnusError, RHash, Symbol, TryConvert, Value};
+use wreq_util::{Emulation, Platform, Profile};
+
+use crate::params::key_string;
+
+/// Every profile wreq-util ships, under the names wreq-util gives them.
+const PROFILES: &[(&str, Profile)] = &[
+    // Chrome
+    ("chrome_100", Profile::Chrome100),
+    ("chrome_101", Profile::Chrome101),
+    ("chrome_104", Profile::Chrome104),
+    ("chrome_105", Profile::Chrome105),
+    ("chrome_106", Profile::Chrome106),
+    ("chrome_107", Profile::Chrome107),
+    ("chrome_108", Profile::Chrome108),
+    ("chrome_109", Profile::Chrome109),
+    ("chrome_110", Profile::Chrome110),
+    ("chrome_114", Profile::Chrome114),
+    ("chrome_116", Profile::Chrome116),
+    ("chrome_117", Profile::Chrome117),
+    ("chrome_118", Profile::Chrome118),
+    ("chrome_119", Profile::Chrome119),
+    ("chrome_120", Profile::Chrome120),
+    ("chrome_123", Profile::Chrome123),
+    ("chrome_124", Profile::Chrome124),
+    ("chrome_126", Profile::Chrome126),
+    ("chrome_127", Profile::Chrome127),
+    ("chrome_128", Profile::Chrome128),
+    ("chrome_129", Profile::Chrome129),
+    ("chrome_130", Profile::Chrome130),
+    ("chrome_131", Profile::Chrome131),
+    ("chrome_132", Profile::Chrome132),
+    ("chrome_133", Profile::Chrome133),
+    ("chrome_134", Profile::Chrome134),
+    ("chrome_135", Profile::Chrome135),
+    ("chrome_136", Profile::Chrome136),
+    ("chrome_137", Profile::Chrome137),
+    ("chrome_138", Profile::Chrome138),
+    ("chrome_139", Profile::Chrome139),
+    ("chrome_140", Profile::Chrome140),
+    ("chrome_141", Profile::Chrome141),
+    ("chrome_142", Profile::Chrome142),
+    ("chrome_143", Profile::Chrome143),
+    ("chrome_144", Profile::Chrome144),
+    ("chrome_145", Profile::Chrome145),
+    ("chrome_146", Profile::Chrome146),
+    ("chrome_147", Profile::Chrome147),
+    ("chrome_148", Profile::Chrome148),
+    ("chrome_149", Profile::Chrome149),
+
+    // Edge
+    ("edge_101", Profile::Edge101),
+    ("edge_122", Profile::Edge122),
+    ("edge_127", Profile::Edge127),
+    ("edge_131", Profile::Edge131),
+    ("edge_134", Profile::Edge134),
+    ("edge_135", Profile::Edge135),
+    ("edge_136", Profile::Edge136),
+    ("edge_137", Profile::Edge137),
+    ("edge_138", Profile::Edge138),
+    ("edge_139", Profile::Edge139),
+    ("edge_140", Profile::Edge140),
+    ("edge_141", Profile::Edge141),
+    ("edge_142", Profile::Edge142),
+    ("edge_143", Profile::Edge143),
+    ("edge_144", Profile::Edge144),
+    ("edge_145", Profile::Edge145),
+    ("edge_146", Profile::Edge146),
+    ("edge_147", Profile::Edge147),
+    ("edge_148", Profile::Edge148),
+
+    // Opera
+    ("opera_116", Profile::Opera116),
+    ("opera_117", Profile::Opera117),
+    ("opera_118", Profile::Opera118),
+    ("opera_119", Profile::Opera119),
+    ("opera_120", Profile::Opera120),
+    ("opera_121", Profile::Opera121),
+    ("opera_122", Profile::Opera122),
+    ("opera_123", Profile::Opera123),
+    ("opera_124", Profile::Opera124),
+    ("opera_125", Profile::Opera125),
+    ("opera_126", Profile::Opera126),
+    ("opera_127", Profile::Opera127),
+    ("opera_128", Profile::Opera128),
+    ("opera_129", Profile::Opera129),
+    ("opera_130", Profile::Opera130),
+    ("opera_131", Profile::Opera131),
+
+    // Firefox
+    ("firefox_109", Profile::Firefox109),
+    ("firefox_117", Profile::Firefox117),
+    ("firefox_128", Profile::Firefox128),
+    ("firefox_133", Profile::Firefox133),
+    ("firefox_135", Profile::Firefox135),
+    ("firefox_private_135", Profile::FirefoxPrivate135),
+    ("firefox_android_135", Profile::FirefoxAndroid135),
+    ("firefox_136", Profile::Firefox136),
+    ("firefox_private_136", Profile::FirefoxPrivate136),
+    ("firefox_139", Profile::Firefox139),
+    ("firefox_142", Profile::Firefox142),
+    ("firefox_143", Profile::Firefox143),
+    ("firefox_144", Profile::Firefox144),
+    ("firefox_145", Profile::Firefox145),
+    ("firefox_146", Profile::Firefox146),
+    ("firefox_147", Profile::Firefox147),
+    ("firefox_148", Profile::Firefox148),
+    ("firefox_149", Profile::Firefox149),
+    ("firefox_150", Profile::Firefox150),
+    ("firefox_151", Profile::Firefox151),
+
+    // Safari
+    ("safari_ios_17.2", Profile::SafariIos17_2),
+    ("safari_ios_17.4.1", Profile::SafariIos17_4_1),
+    ("safari_ios_16.5", Profile::SafariIos16_5),
+    ("safari_15.3", Profile::Safari15_3),
+    ("safari_15.5", Profile::Safari15_5),
+    ("safari_15.6.1", Profile::Safari15_6_1),
+    ("safari_16", Profile::Safari16),
+    ("safari_16.5", Profile::Safari16_5),
+    ("safari_17.0", Profile::Safari17_0),
+    ("safari_17.2.1", Profile::Safari17_2_1),
+    ("safari_17.4.1", Profile::Safari17_4_1),
+    ("safari_17.5", Profile::Safari17_5),
+    ("safari_17.6", Profile::Safari17_6),
+    ("safari_18", Profile::Safari18),
+    ("safari_ipad_18", Profile::SafariIPad18),
+    ("safari_18.2", Profile::Safari18_2),
+    ("safari_ios_18.1.1", Profile::SafariIos18_1_1),
+    ("safari_18.3", Profile::Safari18_3),
+    ("safari_18.3.1", Profile::Safari18_3_1),
+    ("safari_18.5", Profile::Safari18_5),
+    ("safari_26", Profile::Safari26),
+    ("safari_26.1", Profile::Safari26_1),
+    ("safari_26.2", Profile::Safari26_2),
+    ("safari_26.3", Profile::Safari26_3),
+    ("safari_26.4", Profile::Safari26_4),
+    ("safari_ipad_26", Profile::SafariIPad26),
+    ("safari_ipad_26.2", Profile::SafariIpad26_2),
+    ("safari_ios_26", Profile::SafariIos26),
+    ("safari_ios_26.2", Profile::SafariIos26_2),
+
+    // OkHttp
+    ("okhttp_3.9", Profile::OkHttp3_9),
+    ("okhttp_3.11", Profile::OkHttp3_11),
+    ("okhttp_3.13", Profile::OkHttp3_13),
+    ("okhttp_3.14", Profile::OkHttp3_14),
+    ("okhttp_4.9", Profile::OkHttp4_9),
+    ("okhttp_4.10", Profile::OkHttp4_10),
+    ("okhttp_4.12", Profile::OkHttp4_12),
+    ("okhttp_5", Profile::OkHttp5),
+];
+
+/// Operating systems for `os:`.
+const PLATFORMS: &[(&str, Platform)] = &[
+    ("windows", Platform::Windows),
+    ("macos", Platform::MacOS),
+    ("linux", Platform::Linux),
+    ("android", Platform::Android),
+    ("ios", Platform::IOS),
+];
+
+/// How a profile is applied: `os:` picks the operating system named in the
+/// User-Agent and client hints, `skip_headers:` leaves out the profile's
+/// default headers and `skip_http2:` its HTTP/2 settings. The TLS fingerprint
+/// is kept either way.
+#[derive(Default)]
+pub struct Options {
+    platform: Platform,
+    skip_headers: bool,
+    skip_http2: bool,
+}
+
+impl Options {
+    /// Takes one option, returning false for a key that isn't one.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<bool, MagnusError> {
+        match key {
+            "os" => {
+                let name = Symbol::try_convert(value)?.name()?;
+                self.platform = find_platform(&name).ok_or_else(|| {
+                    MagnusError::new(
+                        exception::arg_error(),
+                        format!(
+                            "unknown os: :{} (expected :windows, :macos, :linux, :android or :ios)",
+                            name
+                        ),
+                    )
+                })?;
+            }
+            "skip_headers" => self.skip_headers = value.to_bool(),
+            "skip_http2" => self.skip_http2 = value.to_bool(),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn build(&self, profile: Profile) -> Emulation {
+        Emulation::builder()
+            .profile(profile)
+            .platform(self.platform)
+            .headers(!self.skip_headers)
+            .http2(!self.skip_http2)
+            .build()
+    }
+}
+
+/// Reads `emulate`'s arguments: a profile name, then optionally `os:`,
+/// `skip_headers:` and `skip_http2:`.
+pub fn from_args(args: &[Value]) -> Result<Emulation, MagnusError> {
+    let (name, hash) = match args {
+        [name] => (*name, None),
+        [name, hash] => (*name, Some(RHash::try_convert(*hash)?)),
+        _ => {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                format!("wrong number of arguments (given {}, expected 1..2)", args.len()),
+            ))
+        }
+    };
+    let profile = profile_from_value(name)?;
+
+    let mut options = Options::default();
+    if let Some(hash) = hash {
+        hash.foreach(|key: Symbol, value: Value| {
+            let key = key.name()?;
+            if !options.set(&key, value)? {
+                return Err(unknown_keyword(&key));
+            }
+            Ok(ForEach::Continue)
+        })?;
+    }
+    Ok(options.build(profile))
+}
+
+pub fn unknown_keyword(key: &str) -> MagnusError {
+    MagnusError::new(exception::arg_error(), format!("unknown keyword: :{}", key))
+}
+
+fn find_platform(name: &str) -> Option<Platform> {
+    PLATFORMS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, platform)| platform)
+}
+
+/// Looks up a profile by name: `:chrome_134`, `"safari_ios_17.4.1"`. Dots in
+/// version numbers may also be written as underscores (`:safari_ios_17_4_1`),
+/// which saves quoting the Symbol.
+pub fn profile_from_value(value: Value) -> Result<Profile, MagnusError> {
+    let name = key_string(value)?;
+    find(&name).ok_or_else(|| {
+        MagnusError::new(
+            exception::arg_error(),
+            format!("unknown emulation: {:?} (see Wreq::HTTP.emulations)", name),
+        )
+    })
+}
+
+fn find(name: &str) -> Option<Profile> {
+    let wanted = name.to_ascii_lowercase().replace('.', "_");
+    PROFILES
+        .iter()
+        .find(|(known, _)| known.replace('.', "_") == wanted)
+        .map(|&(_, profile)| profile)
+}
+
+/// Profile names in wreq-util's order, for `Wreq::HTTP.emulations`.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PROFILES.iter().map(|&(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_profile_has_a_name() {
+        assert_eq!(PROFILES.len(), Profile::VARIANTS.len());
+        for profile in Profile::VARIANTS {
+            assert!(PROFILES.iter().any(|(_, known)| known == profile), "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn test_names_stay_unique_with_underscores() {
+        for (name, profile) in PROFILES {
+            assert_eq!(find(name), Some(*profile));
+            assert_eq!(find(&name.replace('.', "_")), Some(*profile));
+        }
+    }
+
+    #[test]
+    fn test_every_platform_has_a_name() {
+        assert_eq!(PLATFORMS.len(), Platform::VARIANTS.len());
+        assert_eq!(find_platform("windows"), Some(Platform::Windows));
+        assert_eq!(find_platform("ios"), Some(Platform::IOS));
+        assert_eq!(find_platform("beos"), None);
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("chrome_134"), Some(Profile::Chrome134));
+        assert_eq!(find("safari_ios_17.4.1"), Some(Profile::SafariIos17_4_1));
+        assert_eq!(find("safari_ios_17_4_1"), Some(Profile::SafariIos17_4_1));
+        assert_eq!(find("OkHttp_5"), Some(Profile::OkHttp5));
+        assert_eq!(find("chrome_1"), None);
+    }
+}
@@ -0,0 +1,332 @@
+use magnus::exception::{Exception, ExceptionClass};
+use magnus::value::{Lazy, ReprValue};
+use magnus::{Attr, Class, Error as MagnusError, Module, Object, RModule, RObject, Ruby, Symbol};
+use std::error::Error as StdError;
+use std::io;
+use std::time::Duration;
+use wreq::Error as WreqError;
+
+// Exception hierarchy, mirroring http.rb's class names so `rescue HTTP::TimeoutError`
+// keeps working after switching gems. The base class inherits from RuntimeError,
+// which is what every failure used to raise.
+//
+//   Wreq::HTTP::Error < RuntimeError
+//   ├── ConnectionError
+//   │   ├── TLSError
+//   │   └── ProxyError
+//   ├── TimeoutError
+//   ├── RedirectError
+//   ├── DecompressionError
+//   ├── BodyReadError
+//   │   └── BodyTooLargeError
+//   ├── InterruptError
+//   └── StateError
+
+fn http_module(ruby: &Ruby) -> RModule {
+    ruby.define_module("Wreq")
+        .and_then(|wreq| wreq.define_module("HTTP"))
+        .expect("failed to define Wreq::HTTP")
+}
+
+fn define_error(ruby: &Ruby, name: &str, superclass: ExceptionClass) -> ExceptionClass {
+    http_module(ruby)
+        .define_error(name, superclass)
+        .unwrap_or_else(|e| panic!("failed to define Wreq::HTTP::{}: {}", name, e))
+}
+
+static ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "Error", ruby.exception_runtime_error()));
+static CONNECTION_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "ConnectionError", ruby.get_inner(&ERROR)));
+static TLS_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "TLSError", ruby.get_inner(&CONNECTION_ERROR)));
+static PROXY_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "ProxyError", ruby.get_inner(&CONNECTION_ERROR)));
+static TIMEOUT_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "TimeoutError", ruby.get_inner(&ERROR)));
+static REDIRECT_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "RedirectError", ruby.get_inner(&ERROR)));
+static DECOMPRESSION_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "DecompressionError", ruby.get_inner(&ERROR)));
+static BODY_READ_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "BodyReadError", ruby.get_inner(&ERROR)));
+static BODY_TOO_LARGE_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "BodyTooLargeError", ruby.get_inner(&BODY_READ_ERROR)));
+static INTERRUPT_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "InterruptError", ruby.get_inner(&ERROR)));
+static STATE_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "StateError", ruby.get_inner(&ERROR)));
+
+pub fn define_exceptions(ruby: &Ruby) -> Result<(), MagnusError> {
+    let error = ruby.get_inner(&ERROR);
+    for attr in ["url", "http_method", "elapsed", "cause_kind", "errno", "source_chain"] {
+        error.define_attr(attr, Attr::Read)?;
+    }
+
+    Lazy::force(&CONNECTION_ERROR, ruby);
+    Lazy::force(&TLS_ERROR, ruby);
+    Lazy::force(&PROXY_ERROR, ruby);
+    Lazy::force(&TIMEOUT_ERROR, ruby);
+    ruby.get_inner(&REDIRECT_ERROR).define_attr("redirects", Attr::Read)?;
+    Lazy::force(&DECOMPRESSION_ERROR, ruby);
+    Lazy::force(&BODY_READ_ERROR, ruby);
+    Lazy::force(&BODY_TOO_LARGE_ERROR, ruby);
+    Lazy::force(&INTERRUPT_ERROR, ruby);
+    Lazy::force(&STATE_ERROR, ruby);
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    Generic,
+    Connection,
+    Tls,
+    Proxy,
+    Timeout,
+    Redirect,
+    Decompression,
+    BodyRead,
+    /// A max_body_size / max_decompressed_size / max_compression_ratio limit was hit
+    BodyTooLarge,
+    Interrupted,
+    /// A streamed body used in a way its state doesn't allow
+    State,
+}
+
+impl ErrorKind {
+    /// Classifies a `wreq::Error`. Order matters: a TLS handshake that times out is a
+    /// timeout, and a failed proxy CONNECT is reported as a proxy error even though
+    /// wreq also flags it as a connect error.
+    pub fn classify(err: &WreqError) -> Self {
+        if err.is_timeout() {
+            ErrorKind::Timeout
+        } else if err.is_proxy_connect() {
+            ErrorKind::Proxy
+        } else if err.is_tls() || (err.is_connect() && mentions_tls(err)) {
+            ErrorKind::Tls
+        } else if err.is_connect() || err.is_dns() || err.is_connection_reset() {
+            ErrorKind::Connection
+        } else if err.is_redirect() {
+            ErrorKind::Redirect
+        } else if err.is_body() || err.is_decode() {
+            ErrorKind::BodyRead
+        } else {
+            ErrorKind::Generic
+        }
+    }
+
+    fn exception_class(self, ruby: &Ruby) -> ExceptionClass {
+        match self {
+            ErrorKind::Generic => ruby.get_inner(&ERROR),
+            ErrorKind::Connection => ruby.get_inner(&CONNECTION_ERROR),
+            ErrorKind::Tls => ruby.get_inner(&TLS_ERROR),
+            ErrorKind::Proxy => ruby.get_inner(&PROXY_ERROR),
+            ErrorKind::Timeout => ruby.get_inner(&TIMEOUT_ERROR),
+            ErrorKind::Redirect => ruby.get_inner(&REDIRECT_ERROR),
+            ErrorKind::Decompression => ruby.get_inner(&DECOMPRESSION_ERROR),
+            ErrorKind::BodyRead => ruby.get_inner(&BODY_READ_ERROR),
+            ErrorKind::BodyTooLarge => ruby.get_inner(&BODY_TOO_LARGE_ERROR),
+            ErrorKind::Interrupted => ruby.get_inner(&INTERRUPT_ERROR),
+            ErrorKind::State => ruby.get_inner(&STATE_ERROR),
+        }
+    }
+}
+
+/// wreq reports handshake failures as plain connect errors, so look for the TLS
+/// layer in the source chain.
+fn mentions_tls(err: &(dyn StdError + 'static)) -> bool {
+    let mut source = err.source();
+    while let Some(inner) = source {
+        let message = inner.to_string().to_ascii_lowercase();
+        if ["tls", "ssl", "certificate", "handshake"]
+            .iter()
+            .any(|needle| message.contains(needle))
+        {
+            return true;
+        }
+        source = inner.source();
+    }
+    false
+}
+
+/// A request failure captured while the GVL is released. It only holds plain Rust
+/// data and is turned into a Ruby exception once the GVL has been re-acquired.
+#[derive(Debug)]
+pub struct RequestError {
+    kind: ErrorKind,
+    message: String,
+    url: Option<String>,
+    method: Option<String>,
+    elapsed: Option<Duration>,
+    cause_kind: Option<String>,
+    errno: Option<i32>,
+    source_chain: Vec<String>,
+    // Redirects followed before a RedirectError
+    redirects: Option<usize>,
+}
+
+impl RequestError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            url: None,
+            method: None,
+            elapsed: None,
+            cause_kind: None,
+            errno: None,
+            source_chain: Vec::new(),
+            redirects: None,
+        }
+    }
+
+    pub fn from_wreq(err: &WreqError, context: &str) -> Self {
+        let mut request_error = Self::new(ErrorKind::classify(err), format!("{}: {}", context, err));
+        // For redirect failures this is the hop that failed rather than the original URL.
+        request_error.url = err.uri().map(|uri| uri.to_string());
+
+        let mut source = err.source();
+        while let Some(inner) = source {
+            request_error.source_chain.push(inner.to_string());
+            if let Some(io_err) = inner.downcast_ref::<io::Error>() {
+                if request_error.cause_kind.is_none() {
+                    request_error.cause_kind = Some(snake_case(&format!("{:?}", io_err.kind())));
+                    request_error.errno = io_err.raw_os_error();
+                }
+            }
+            source = inner.source();
+        }
+
+        request_error
+    }
+
+    pub fn decompression(err: impl std::fmt::Display) -> Self {
+        Self::new(ErrorKind::Decompression, format!("Decompression failed: {}", err))
+    }
+
+    /// Attaches the request being executed. A URL already taken from the wreq error
+    /// is kept.
+    pub fn with_request(mut self, method: &str, url: &str, elapsed: Duration) -> Self {
+        self.url.get_or_insert_with(|| url.to_string());
+        self.method = Some(method.to_string());
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// Records how many redirect responses came in before the failure. For a
+    /// RedirectError the last of them is the one that was refused, so it isn't
+    /// counted as followed.
+    pub fn with_redirects(mut self, seen: usize) -> Self {
+        if self.kind == ErrorKind::Redirect {
+            self.redirects = Some(seen.saturating_sub(1));
+        }
+        self
+    }
+
+    /// Must be called with the GVL held.
+    pub fn into_magnus(self) -> MagnusError {
+        let ruby = Ruby::get().expect("Ruby exception raised from a non-Ruby thread");
+        let class = self.kind.exception_class(&ruby);
+        match self.build_exception(class) {
+            Ok(exception) => exception.into(),
+            Err(e) => e,
+        }
+    }
+
+    fn build_exception(self, class: ExceptionClass) -> Result<Exception, MagnusError> {
+        let exception = class.new_instance((self.message,))?;
+        if let Some(object) = RObject::from_value(exception.as_value()) {
+            object.ivar_set("@url", self.url)?;
+            object.ivar_set("@http_method", self.method)?;
+            object.ivar_set("@elapsed", self.elapsed.map(|d| d.as_secs_f64()))?;
+            object.ivar_set("@cause_kind", self.cause_kind.map(Symbol::new))?;
+            object.ivar_set("@errno", self.errno)?;
+            object.ivar_set("@source_chain", self.source_chain)?;
+            if let Some(redirects) = self.redirects {
+                object.ivar_set("@redirects", redirects)?;
+            }
+        }
+        Ok(exception)
+    }
+}
+
+/// `ConnectionRefused` -> `connection_refused`
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+    use std::io;
+
+    #[derive(Debug)]
+    struct Wrapped(io::Error);
+
+    impl fmt::Display for Wrapped {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("client error (Connect)")
+        }
+    }
+
+    impl StdError for Wrapped {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_mentions_tls_finds_handshake_in_source_chain() {
+        let err = Wrapped(io::Error::other("TLS handshake failed: certificate verify failed"));
+        assert!(mentions_tls(&err));
+    }
+
+    #[test]
+    fn test_mentions_tls_ignores_plain_connect_errors() {
+        let err = Wrapped(io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused"));
+        assert!(!mentions_tls(&err));
+    }
+
+    #[test]
+    fn test_snake_case_io_error_kind() {
+        assert_eq!(snake_case("ConnectionRefused"), "connection_refused");
+        assert_eq!(snake_case("TimedOut"), "timed_out");
+        assert_eq!(snake_case("Other"), "other");
+    }
+
+    #[test]
+    fn test_with_request_keeps_url_from_error() {
+        let mut err = RequestError::new(ErrorKind::Redirect, "too many redirects");
+        err.url = Some("https://example.com/loop".to_string());
+        let err = err.with_request("GET", "https://example.com/start", Duration::from_millis(5));
+        assert_eq!(err.url.as_deref(), Some("https://example.com/loop"));
+        assert_eq!(err.method.as_deref(), Some("GET"));
+    }
+
+    #[test]
+    fn test_redirects_exclude_the_refused_one() {
+        let err = RequestError::new(ErrorKind::Redirect, "too many redirects").with_redirects(11);
+        assert_eq!(err.redirects, Some(10));
+
+        let err = RequestError::new(ErrorKind::Connection, "connection refused").with_redirects(2);
+        assert_eq!(err.redirects, None);
+    }
+
+    #[test]
+    fn test_request_error_keeps_kind() {
+        let err = RequestError::new(ErrorKind::Decompression, "bad gzip header");
+        assert_eq!(err.kind, ErrorKind::Decompression);
+    }
+}
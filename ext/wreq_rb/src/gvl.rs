@@ -1,16 +1,61 @@
-use rb_sys::rb_thread_call_without_gvl;
+use rb_sys::rb_thread_call_without_gvl2;
 use std::ffi::c_void;
+use std::future::Future;
+use tokio::runtime::Runtime;
+use tokio::sync::Notify;
 
-pub fn without_gvl<F, R>(f: F) -> R
+/// Runs `future` to completion on `runtime` with the GVL released.
+///
+/// Returns `None` if Ruby interrupted the thread (`Thread#raise`, `Thread#kill`,
+/// `Timeout.timeout`, signals). The future is dropped at that point, which cancels
+/// the in-flight request. The caller is expected to let Ruby handle the pending
+/// interrupt once the GVL is held again.
+pub fn block_on_interruptible<F>(runtime: &Runtime, future: F) -> Option<F::Output>
 where
-    F: FnOnce() -> R + Send,
-    R: Send,
+    F: Future + Send,
+    F::Output: Send,
 {
     // Check env var bypass
     if std::env::var("WREQ_RB_NO_GVL_RELEASE").is_ok() {
-        return f();
+        return Some(runtime.block_on(future));
     }
 
+    let cancel = Notify::new();
+    let run = || {
+        runtime.block_on(async {
+            tokio::select! {
+                output = future => Some(output),
+                _ = cancel.notified() => None,
+            }
+        })
+    };
+
+    without_gvl(run, Some(unblock), &cancel as *const Notify as *mut c_void).flatten()
+}
+
+/// Unblocking function Ruby calls from another thread when it wants this one back.
+/// `notify_one` stores a permit, so an interrupt arriving before the future is first
+/// polled is not lost.
+unsafe extern "C" fn unblock(data: *mut c_void) {
+    let cancel = unsafe { &*(data as *const Notify) };
+    cancel.notify_one();
+}
+
+/// Calls `f` without the GVL. Returns `None` when Ruby had an interrupt pending on
+/// entry, in which case `f` is never called.
+///
+/// `rb_thread_call_without_gvl2` is used instead of `rb_thread_call_without_gvl`
+/// because it does not process interrupts itself: raising from inside it would
+/// unwind through these Rust frames.
+fn without_gvl<F, R>(
+    f: F,
+    ubf: Option<unsafe extern "C" fn(*mut c_void)>,
+    ubf_data: *mut c_void,
+) -> Option<R>
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
     extern "C" fn call<F, R>(data: *mut c_void) -> *mut c_void
     where
         F: FnOnce() -> R + Send,
@@ -22,8 +67,12 @@ where
     }
 
     let data = Box::into_raw(Box::new(f)) as *mut c_void;
-    let result =
-        unsafe { rb_thread_call_without_gvl(Some(call::<F, R>), data, None, std::ptr::null_mut()) };
+    let result = unsafe { rb_thread_call_without_gvl2(Some(call::<F, R>), data, ubf, ubf_data) };
+    if result.is_null() {
+        // Interrupted before `f` ran, so it still owns `data`.
+        drop(unsafe { Box::from_raw(data as *mut F) });
+        return None;
+    }
     let result: Box<R> = unsafe { Box::from_raw(result as *mut R) };
-    *result
+    Some(*result)
 }
@@ -0,0 +1,231 @@
+use magnus::value::ReprValue;
+use magnus::{RArray, RHash, RString, Ruby, Value};
+use std::sync::Arc;
+use wreq::header::{HeaderMap, HeaderValue};
+
+/// Response headers, backed by the `HeaderMap` wreq returned.
+///
+/// Lookups are case-insensitive and repeated headers (`Set-Cookie`, `Link`, `Vary`)
+/// keep every value. Iteration follows the order names first appeared on the wire,
+/// with repeated values grouped under their name: `HeaderMap` keeps each name's
+/// values in wire order but not how they interleaved with other names, and wreq
+/// doesn't keep the raw header block.
+#[magnus::wrap(class = "Wreq::HTTP::Headers")]
+pub struct RbHeaders {
+    map: Arc<HeaderMap>,
+}
+
+impl RbHeaders {
+    pub fn new(map: Arc<HeaderMap>) -> Self {
+        Self { map }
+    }
+
+    /// `nil` if absent, a String for a single value, an Array when repeated.
+    pub fn aref(ruby: &Ruby, rb_self: &Self, name: String) -> Value {
+        let mut values = rb_self.map.get_all(name.as_str()).iter();
+        match (values.next(), values.next()) {
+            (None, _) => ruby.qnil().as_value(),
+            (Some(value), None) => value_to_rstring(value).as_value(),
+            (Some(_), Some(_)) => rb_self.get(name).as_value(),
+        }
+    }
+
+    /// All values for `name`, in the order they were received.
+    pub fn get(&self, name: String) -> RArray {
+        let values = RArray::new();
+        for value in self.map.get_all(name.as_str()) {
+            let _ = values.push(value_to_rstring(value));
+        }
+        values
+    }
+
+    /// `[name, value]` pairs, one per header line.
+    pub fn to_a(&self) -> RArray {
+        let pairs = RArray::with_capacity(self.map.len());
+        for (name, value) in header_lines(&self.map) {
+            let _ = pairs.push((name, value_to_rstring(value)));
+        }
+        pairs
+    }
+
+    /// Name => String, or Array for repeated headers.
+    pub fn to_h(ruby: &Ruby, rb_self: &Self) -> RHash {
+        let hash = RHash::new();
+        for name in rb_self.map.keys() {
+            let _ = hash.aset(name.as_str(), Self::aref(ruby, rb_self, name.to_string()));
+        }
+        hash
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.map.keys().map(|name| name.to_string()).collect()
+    }
+
+    pub fn include(&self, name: String) -> bool {
+        self.map.contains_key(name.as_str())
+    }
+
+    pub fn size(&self) -> usize {
+        self.map.keys_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// One entry per header line: names in the order they first appeared, each
+/// name's values in the order they were received.
+fn header_lines(map: &HeaderMap) -> impl Iterator<Item = (&str, &HeaderValue)> {
+    map.iter().map(|(name, value)| (name.as_str(), value))
+}
+
+/// Header values are raw bytes: visible ASCII/UTF-8 comes back as a UTF-8 String,
+/// anything else as an ASCII-8BIT String with the bytes untouched.
+fn value_to_rstring(value: &HeaderValue) -> RString {
+    match std::str::from_utf8(value.as_bytes()) {
+        Ok(text) => RString::new(text),
+        Err(_) => RString::from_slice(value.as_bytes()),
+    }
+}
+
+/// Filename from a Content-Disposition value. `filename*` (RFC 6266 / 8187) is
+/// preferred over `filename`. Directory parts are stripped so the result is always
+/// a bare file name.
+pub fn disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((name, raw)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                if let Some(name) = decode_ext_value(raw.trim()).and_then(|name| sanitize_filename(&name)) {
+                    return Some(name);
+                }
+            }
+            "filename" => plain = Some(unquote(raw.trim())),
+            _ => {}
+        }
+    }
+    plain.and_then(|name| sanitize_filename(&name))
+}
+
+/// Splits on `;` outside quoted strings.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+/// `UTF-8''na%C3%AFve.txt` -> `naïve.txt`. Non-UTF-8 charsets are read as ISO-8859-1.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let bytes = urlencoding::decode_binary(parts.next()?.as_bytes());
+    if charset.eq_ignore_ascii_case("utf-8") {
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        Some(bytes.iter().map(|&b| b as char).collect())
+    }
+}
+
+/// Keeps only the last path component and rejects names that aren't a file.
+pub fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    match name {
+        "" | "." | ".." => None,
+        name => Some(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleaved_repeated_headers() {
+        let mut map = HeaderMap::new();
+        map.append("set-cookie", HeaderValue::from_static("a=1"));
+        map.append("x-request-id", HeaderValue::from_static("42"));
+        map.append("set-cookie", HeaderValue::from_static("b=2"));
+        map.append("vary", HeaderValue::from_static("Accept"));
+
+        let lines: Vec<_> = header_lines(&map)
+            .map(|(name, value)| (name, value.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("set-cookie", "a=1"),
+                ("set-cookie", "b=2"),
+                ("x-request-id", "42"),
+                ("vary", "Accept"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disposition_plain_filename() {
+        assert_eq!(
+            disposition_filename("attachment; filename=\"report.pdf\"").as_deref(),
+            Some("report.pdf")
+        );
+        assert_eq!(disposition_filename("attachment; filename=data.csv").as_deref(), Some("data.csv"));
+    }
+
+    #[test]
+    fn test_disposition_prefers_extended_filename() {
+        let value = "attachment; filename=\"naive.txt\"; filename*=UTF-8''na%C3%AFve.txt";
+        assert_eq!(disposition_filename(value).as_deref(), Some("naïve.txt"));
+    }
+
+    #[test]
+    fn test_disposition_latin1_extended_filename() {
+        let value = "attachment; filename*=iso-8859-1'en'caf%E9.txt";
+        assert_eq!(disposition_filename(value).as_deref(), Some("café.txt"));
+    }
+
+    #[test]
+    fn test_disposition_quoted_semicolon() {
+        let value = "attachment; filename=\"a;b.txt\"";
+        assert_eq!(disposition_filename(value).as_deref(), Some("a;b.txt"));
+    }
+
+    #[test]
+    fn test_disposition_strips_directories() {
+        assert_eq!(
+            disposition_filename("attachment; filename=\"../../etc/passwd\"").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(disposition_filename("attachment; filename=\"..\"").as_deref(), None);
+    }
+
+    #[test]
+    fn test_disposition_without_filename() {
+        assert_eq!(disposition_filename("inline"), None);
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wreq::header::HeaderMap;
+use wreq::redirect::{History, Policy};
+use wreq::Response as WreqResponse;
+
+use crate::headers::RbHeaders;
+use crate::timings;
+
+/// Wraps the client's redirect policy so the time each redirect response
+/// arrived is recorded; the decision itself is left to `policy`.
+pub fn traced(policy: Policy) -> Policy {
+    Policy::custom(move |attempt| {
+        timings::record_redirect();
+        policy.redirect(attempt)
+    })
+}
+
+/// One redirect followed on the way to the final response.
+#[derive(Clone, Debug)]
+pub struct Hop {
+    pub status: u16,
+    /// URL that was requested and answered with the redirect
+    pub url: String,
+    /// Where the redirect pointed, resolved against `url`
+    pub location: String,
+    pub headers: Arc<HeaderMap>,
+    /// From sending the request for `url` to its redirect response
+    pub elapsed: Duration,
+}
+
+/// Redirects wreq followed for `response`, oldest first. `redirected_at` holds
+/// when each redirect response arrived; hops without a timestamp get zero.
+pub fn hops(response: &WreqResponse, started_at: Instant, redirected_at: &[Instant]) -> Vec<Hop> {
+    let Some(history) = response.extensions().get::<History>() else {
+        return Vec::new();
+    };
+
+    let durations = hop_durations(started_at, redirected_at);
+    history
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| Hop {
+            status: entry.status.as_u16(),
+            url: entry.previous.to_string(),
+            location: entry.uri.to_string(),
+            headers: Arc::new(entry.headers.clone()),
+            elapsed: durations.get(i).copied().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Time between consecutive redirects, the first one counted from `started_at`.
+fn hop_durations(started_at: Instant, redirected_at: &[Instant]) -> Vec<Duration> {
+    let mut previous = started_at;
+    redirected_at
+        .iter()
+        .map(|&at| at.saturating_duration_since(std::mem::replace(&mut previous, at)))
+        .collect()
+}
+
+/// `Wreq::HTTP::Redirect`, one entry of `Response#history`.
+#[magnus::wrap(class = "Wreq::HTTP::Redirect")]
+pub struct RbRedirect {
+    hop: Hop,
+}
+
+impl RbRedirect {
+    pub fn new(hop: Hop) -> Self {
+        Self { hop }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.hop.status
+    }
+
+    pub fn uri(&self) -> String {
+        self.hop.url.clone()
+    }
+
+    pub fn location(&self) -> String {
+        self.hop.location.clone()
+    }
+
+    pub fn headers(&self) -> RbHeaders {
+        RbHeaders::new(Arc::clone(&self.hop.headers))
+    }
+
+    /// Seconds from sending this hop's request to its redirect response.
+    pub fn elapsed(&self) -> f64 {
+        self.hop.elapsed.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hop_durations() {
+        let started_at = Instant::now();
+        let redirected_at = [
+            started_at + Duration::from_millis(100),
+            started_at + Duration::from_millis(250),
+        ];
+        assert_eq!(
+            hop_durations(started_at, &redirected_at),
+            vec![Duration::from_millis(100), Duration::from_millis(150)]
+        );
+        assert!(hop_durations(started_at, &[]).is_empty());
+    }
+}
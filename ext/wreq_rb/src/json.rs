@@ -0,0 +1,370 @@
+use magnus::value::{Qfalse, Qtrue, ReprValue};
+use magnus::{
+    exception, Error as MagnusError, ExceptionClass, Float, Integer, Module, RArray, RHash, RModule,
+    RString, Ruby, Symbol, TryConvert, Value,
+};
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+
+/// Ruby's `JSON.parse` default.
+const DEFAULT_MAX_NESTING: usize = 100;
+
+/// Key of the one-entry map serde_json's `arbitrary_precision` passes a number
+/// that doesn't fit an i64/u64 as, digits untouched.
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Options of `Response#json`, named like `JSON.parse`'s.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParseOptions {
+    pub symbolize_names: bool,
+    /// `None` when disabled with `max_nesting: false`
+    pub max_nesting: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            symbolize_names: false,
+            max_nesting: Some(DEFAULT_MAX_NESTING),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Reads `symbolize_names:` and `max_nesting:` (an Integer, or `false`/`0` for
+    /// no limit). Any other key is an ArgumentError.
+    pub fn from_args(args: &[Value]) -> Result<Self, MagnusError> {
+        let mut options = Self::default();
+        let Some(&hash) = args.first() else {
+            return Ok(options);
+        };
+        if args.len() > 1 {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                format!("wrong number of arguments (given {}, expected 0..1)", args.len()),
+            ));
+        }
+
+        let hash = RHash::try_convert(hash)?;
+        hash.foreach(|key: Symbol, value: Value| {
+            match &*key.name()? {
+                "symbolize_names" => options.symbolize_names = value.to_bool(),
+                "max_nesting" => {
+                    options.max_nesting = if value.to_bool() {
+                        Some(usize::try_convert(value)?).filter(|&max| max > 0)
+                    } else {
+                        None
+                    };
+                }
+                name => {
+                    return Err(MagnusError::new(
+                        exception::arg_error(),
+                        format!("unknown keyword: :{}", name),
+                    ))
+                }
+            }
+            Ok(magnus::r_hash::ForEach::Continue)
+        })?;
+        Ok(options)
+    }
+}
+
+/// Parses a JSON document straight into Ruby objects, without building an
+/// intermediate tree. Raises `JSON::ParserError` (or `JSON::NestingError`) like
+/// `JSON.parse` would. A leading UTF-8 BOM is skipped.
+pub fn parse(ruby: &Ruby, json: &[u8], options: ParseOptions) -> Result<Value, MagnusError> {
+    let json = json.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(json);
+    let nesting_error = Cell::new(false);
+    let seed = RubySeed {
+        ruby,
+        options,
+        depth: 0,
+        nesting_error: &nesting_error,
+    };
+
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let result = seed
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value));
+
+    result.map_err(|err| {
+        let class = if nesting_error.get() { "NestingError" } else { "ParserError" };
+        MagnusError::new(json_error(ruby, class), err.to_string())
+    })
+}
+
+/// `JSON::ParserError` and friends; falls back to ArgumentError if the json gem
+/// isn't loaded.
+fn json_error(ruby: &Ruby, name: &str) -> ExceptionClass {
+    ruby.class_object()
+        .const_get::<_, RModule>("JSON")
+        .and_then(|json| json.const_get::<_, ExceptionClass>(name))
+        .unwrap_or_else(|_| exception::arg_error())
+}
+
+#[derive(Clone, Copy)]
+struct RubySeed<'a> {
+    ruby: &'a Ruby,
+    options: ParseOptions,
+    // Arrays and objects we're inside of
+    depth: usize,
+    nesting_error: &'a Cell<bool>,
+}
+
+impl RubySeed<'_> {
+    /// Seed for the elements of an array or object one level down.
+    fn nested<E: de::Error>(self) -> Result<Self, E> {
+        let depth = self.depth + 1;
+        if self.options.max_nesting.is_some_and(|max| depth > max) {
+            self.nesting_error.set(true);
+            return Err(E::custom(format!("nesting of {} is too deep", depth)));
+        }
+        Ok(Self { depth, ..self })
+    }
+
+    /// A number that didn't fit an i64/u64: an Integer of any size, or a Float
+    /// when it has a fraction or exponent, as `JSON.parse` returns.
+    fn number(self, number: &str) -> Result<Value, MagnusError> {
+        if number.bytes().all(|b| b == b'-' || b.is_ascii_digit()) {
+            self.ruby.module_kernel().funcall("Integer", (number,))
+        } else {
+            let n: f64 = number
+                .parse()
+                .map_err(|e| MagnusError::new(exception::arg_error(), format!("{}: {}", number, e)))?;
+            Ok(self.ruby.float_from_f64(n).as_value())
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for RubySeed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RubySeed<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(self.ruby.qnil().as_value())
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(if value { self.ruby.qtrue().as_value() } else { self.ruby.qfalse().as_value() })
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(self.ruby.integer_from_i64(value).as_value())
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(self.ruby.integer_from_u64(value).as_value())
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(self.ruby.float_from_f64(value).as_value())
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(self.ruby.str_new(value).as_value())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let seed = self.nested()?;
+        let array = self.ruby.ary_new_capa(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element_seed(seed)? {
+            array.push(element).map_err(de::Error::custom)?;
+        }
+        Ok(array.as_value())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut next_key = map.next_key::<Cow<'de, str>>()?;
+        if next_key.as_deref() == Some(NUMBER_TOKEN) {
+            let number = map.next_value::<Cow<'de, str>>()?;
+            return self.number(&number).map_err(de::Error::custom);
+        }
+
+        let seed = self.nested()?;
+        let hash = self.ruby.hash_new();
+        while let Some(key) = next_key {
+            let value = map.next_value_seed(seed)?;
+            let result = if self.options.symbolize_names {
+                hash.aset(self.ruby.to_symbol(&key), value)
+            } else {
+                hash.aset(self.ruby.str_new(&key), value)
+            };
+            result.map_err(de::Error::custom)?;
+            next_key = map.next_key()?;
+        }
+        Ok(hash.as_value())
+    }
+}
+
+/// Serializes a Ruby object to JSON the way `JSON.generate` does, without going
+/// through Ruby source. Hash, Array, String, Symbol, Integer, Float, true, false
+/// and nil are written directly. Any other object goes through, in order: a
+/// `to_json` its class defines itself (what `JSON.generate` would call), its
+/// `as_json`, the generic `to_json` every object gets from the json gem, its `to_s`.
+///
+/// Raises `JSON::GeneratorError` for NaN/Infinity and strings that can't be
+/// encoded as UTF-8, and `JSON::NestingError` past `DEFAULT_MAX_NESTING` levels
+/// (which also stops self-referencing structures).
+pub fn generate(ruby: &Ruby, value: Value) -> Result<String, MagnusError> {
+    let mut generator = Generator { ruby, out: Vec::new() };
+    generator.write(value, 0)?;
+    // Only valid UTF-8 is ever written, but to_json output is taken as is
+    String::from_utf8(generator.out)
+        .map_err(|e| MagnusError::new(json_error(ruby, "GeneratorError"), e.to_string()))
+}
+
+struct Generator<'a> {
+    ruby: &'a Ruby,
+    out: Vec<u8>,
+}
+
+impl Generator<'_> {
+    fn write(&mut self, value: Value, depth: usize) -> Result<(), MagnusError> {
+        if value.is_nil() {
+            self.out.extend_from_slice(b"null");
+        } else if Qtrue::from_value(value).is_some() {
+            self.out.extend_from_slice(b"true");
+        } else if Qfalse::from_value(value).is_some() {
+            self.out.extend_from_slice(b"false");
+        } else if let Some(string) = RString::from_value(value) {
+            let string = string.to_string().map_err(|e| self.error("GeneratorError", e))?;
+            self.write_str(&string);
+        } else if let Some(symbol) = Symbol::from_value(value) {
+            self.write_str(&symbol.name()?);
+        } else if let Some(integer) = Integer::from_value(value) {
+            match integer.to_i64() {
+                Ok(n) => self.out.extend_from_slice(n.to_string().as_bytes()),
+                // Bignum: Integer#to_s is already valid JSON
+                Err(_) => self.out.extend_from_slice(value.to_r_string()?.to_string()?.as_bytes()),
+            }
+        } else if let Some(float) = Float::from_value(value) {
+            let n = float.to_f64();
+            if !n.is_finite() {
+                return Err(self.error("GeneratorError", format!("{} not allowed in JSON", n)));
+            }
+            let n = serde_json::to_string(&n).map_err(|e| self.error("GeneratorError", e))?;
+            self.out.extend_from_slice(n.as_bytes());
+        } else if let Some(array) = RArray::from_value(value) {
+            let depth = self.nested(depth)?;
+            self.out.push(b'[');
+            for (i, element) in array.into_iter().enumerate() {
+                if i > 0 {
+                    self.out.push(b',');
+                }
+                self.write(element, depth)?;
+            }
+            self.out.push(b']');
+        } else if let Some(hash) = RHash::from_value(value) {
+            let depth = self.nested(depth)?;
+            self.out.push(b'{');
+            let mut first = true;
+            hash.foreach(|key: Value, element: Value| {
+                if !std::mem::take(&mut first) {
+                    self.out.push(b',');
+                }
+                self.write_key(key)?;
+                self.out.push(b':');
+                self.write(element, depth)?;
+                Ok(magnus::r_hash::ForEach::Continue)
+            })?;
+            self.out.push(b'}');
+        } else if self.defines_to_json(value)? {
+            self.write_to_json(value)?;
+        } else if value.respond_to("as_json", false)? {
+            let depth = self.nested(depth)?;
+            self.write(value.funcall("as_json", ())?, depth)?;
+        } else if value.respond_to("to_json", false)? {
+            self.write_to_json(value)?;
+        } else {
+            self.write_str(&value.to_r_string()?.to_string()?);
+        }
+        Ok(())
+    }
+
+    /// Whether `value` has a `to_json` of its own, rather than the one the json gem
+    /// (or ActiveSupport, which builds it on `as_json`) defines on Object.
+    fn defines_to_json(&self, value: Value) -> Result<bool, MagnusError> {
+        if !value.respond_to("to_json", false)? {
+            return Ok(false);
+        }
+        let object = self.ruby.class_object();
+        if !object.funcall::<_, _, bool>("method_defined?", ("to_json",))? {
+            return Ok(true);
+        }
+        let owner: Value = value.funcall::<_, _, Value>("method", ("to_json",))?.funcall("owner", ())?;
+        let generic: Value = object.funcall::<_, _, Value>("instance_method", ("to_json",))?.funcall("owner", ())?;
+        Ok(!owner.equal(generic)?)
+    }
+
+    fn write_to_json(&mut self, value: Value) -> Result<(), MagnusError> {
+        let json: RString = value.funcall("to_json", ())?;
+        // SAFETY: the slice is copied before Ruby can run again
+        self.out.extend_from_slice(unsafe { json.as_slice() });
+        Ok(())
+    }
+
+    /// Object keys are strings: Symbols by name, anything else by `to_s`.
+    fn write_key(&mut self, key: Value) -> Result<(), MagnusError> {
+        if let Some(symbol) = Symbol::from_value(key) {
+            self.write_str(&symbol.name()?);
+        } else {
+            let key = key.to_r_string()?.to_string().map_err(|e| self.error("GeneratorError", e))?;
+            self.write_str(&key);
+        }
+        Ok(())
+    }
+
+    fn write_str(&mut self, string: &str) {
+        // Writing a str into a Vec can't fail
+        let _ = serde_json::to_writer(&mut self.out, string);
+    }
+
+    fn nested(&self, depth: usize) -> Result<usize, MagnusError> {
+        let depth = depth + 1;
+        if depth > DEFAULT_MAX_NESTING {
+            return Err(self.error("NestingError", format!("nesting of {} is too deep", depth)));
+        }
+        Ok(depth)
+    }
+
+    fn error(&self, class: &str, message: impl fmt::Display) -> MagnusError {
+        MagnusError::new(json_error(self.ruby, class), message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_json_parse() {
+        let options = ParseOptions::default();
+        assert!(!options.symbolize_names);
+        assert_eq!(options.max_nesting, Some(100));
+    }
+
+    #[test]
+    fn test_big_numbers_keep_their_digits() {
+        // Needs serde_json's arbitrary_precision, or this comes back as a lossy f64
+        let big = "123456789012345678901234567890";
+        let number: serde_json::Number = serde_json::from_str(big).unwrap();
+        assert_eq!(number.to_string(), big);
+        assert!(number.as_u64().is_none());
+
+        let fraction: serde_json::Number = serde_json::from_str("0.1000000000000000055511151231257827").unwrap();
+        assert_eq!(fraction.to_string(), "0.1000000000000000055511151231257827");
+    }
+}
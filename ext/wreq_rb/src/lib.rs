@@ -1,25 +1,51 @@
 use rb_sys::*;
 use magnus::r_hash::ForEach;
+use magnus::encoding::{EncodingCapable, RbEncoding};
+use magnus::value::ReprValue;
 use magnus::{
-    Error as MagnusError, IntoValue, Module, Object, RHash, Symbol, TryConvert, Value, exception,
-    function, method,
+    Error as MagnusError, IntoValue, Module, Object, RArray, RHash, RString, Ruby, Symbol, TryConvert,
+    Value, exception, function, method,
 };
+use bytes::{Bytes, BytesMut};
 use wreq::header::{HeaderMap, HeaderName, HeaderValue};
 use wreq::redirect::Policy;
-use wreq::{Error as WreqError, Response as WreqResponse};
-use wreq_util::Emulation as WreqEmulation;
+use wreq::Response as WreqResponse;
+use wreq_util::{Emulation as WreqEmulation, Profile};
 use std::cell::Cell;
 use std::collections::HashMap;
 use std::collections::hash_map::RandomState;
 use std::hash::{BuildHasher, Hasher};
 use std::num::Wrapping;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 use url::Url;
+use error::{ErrorKind, RequestError};
+use headers::RbHeaders;
+use body::{ChunkReader, RbBody};
+use conn::{ConnectionInfo, ConnectionTracker};
+use timings::{RbTimings, RequestTimings};
+use history::{Hop, RbRedirect};
+use limits::BodyLimits;
+use compress::Compression;
+use params::ArrayStyle;
 mod gvl;
 mod decompress;
+mod charset;
+mod compress;
+mod emulation;
+mod headers;
+mod body;
+mod conn;
+mod timings;
+mod history;
+mod json;
+mod multipart;
+mod params;
+mod upload;
+mod limits;
+mod error;
 use lazy_static::lazy_static;
 
 // Fast random implementation similar to wreq-util crate
@@ -44,55 +70,128 @@ fn fast_random() -> u64 {
 
     RNG.with(|rng| {
         let mut n = rng.get();
-        debug_assert_ne!(n.0, 0);
-        n ^= n >> 12;
-        n ^= n << 25;
-        n ^= n >> 27;
+        let out = xorshift(&mut n);
         rng.set(n);
-        n.0.wrapping_mul(0x2545f4914f6cdd1d)
+        out
     })
 }
 
-fn get_random_desktop_emulation() -> WreqEmulation {
+/// One xorshift64* step.
+fn xorshift(state: &mut Wrapping<u64>) -> u64 {
+    let mut n = *state;
+    debug_assert_ne!(n.0, 0);
+    n ^= n >> 12;
+    n ^= n << 25;
+    n ^= n >> 27;
+    *state = n;
+    n.0.wrapping_mul(0x2545f4914f6cdd1d)
+}
+
+lazy_static! {
+    // `Wreq::HTTP.random_seed` and the generator it started
+    static ref RANDOM_SEED: Mutex<Option<(u64, EmulationRng)>> = Mutex::new(None);
+}
+
+/// Picks which browser a client emulates. Kept apart from `fast_random` so that
+/// with a seed set, the same clients get the same browsers no matter what else
+/// (multipart boundaries) drew random numbers in between.
+#[derive(Clone, Copy, Debug)]
+struct EmulationRng(Wrapping<u64>);
+
+impl EmulationRng {
+    /// Spreads the seed with splitmix64, so small and zero seeds work too.
+    fn from_seed(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        EmulationRng(Wrapping(if z == 0 { 0x9e3779b97f4a7c15 } else { z }))
+    }
+
+    /// Generator for a new client: the next one in the `random_seed` sequence
+    /// when a seed is set, a random one otherwise.
+    fn next_client() -> Self {
+        let mut global = RANDOM_SEED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match &mut *global {
+            Some((_, rng)) => Self::from_seed(rng.next()),
+            None => Self::from_seed(fast_random()),
+        }
+    }
+
+    /// Generator for `HTTP.desktop(seed: 42)` and friends: seeded when `seed:`
+    /// is given, `next_client` otherwise.
+    fn from_args(args: &[Value]) -> Result<Self, MagnusError> {
+        let hash = match args {
+            [] => return Ok(Self::next_client()),
+            [hash] => RHash::try_convert(*hash)?,
+            _ => {
+                return Err(MagnusError::new(
+                    exception::arg_error(),
+                    format!("wrong number of arguments (given {}, expected 0..1)", args.len()),
+                ))
+            }
+        };
+        let mut seed = None;
+        hash.foreach(|key: Symbol, value: Value| {
+            let key = key.name()?;
+            if key != "seed" {
+                return Err(emulation::unknown_keyword(&key));
+            }
+            seed = Some(u64::try_convert(value)?);
+            Ok(ForEach::Continue)
+        })?;
+        Ok(seed.map_or_else(Self::next_client, Self::from_seed))
+    }
+
+    fn next(&mut self) -> u64 {
+        xorshift(&mut self.0)
+    }
+}
+
+/// Sets (or with `None`, clears) the seed emulation picks start from.
+fn set_random_seed(seed: Option<u64>) {
+    let mut global = RANDOM_SEED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *global = seed.map(|seed| (seed, EmulationRng::from_seed(seed)));
+}
+
+fn random_seed() -> Option<u64> {
+    let global = RANDOM_SEED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    global.as_ref().map(|&(seed, _)| seed)
+}
+
+fn get_random_desktop_emulation(rng: &mut EmulationRng) -> Profile {
     let browsers = [
-        WreqEmulation::Chrome134,
-        WreqEmulation::Chrome128,
-        WreqEmulation::Chrome101,
-        WreqEmulation::Firefox135,
-        WreqEmulation::Safari17_0,
+        Profile::Chrome134,
+        Profile::Chrome128,
+        Profile::Chrome101,
+        Profile::Firefox135,
+        Profile::Safari17_0,
     ];
 
-    let index = (fast_random() as usize) % browsers.len();
+    let index = (rng.next() as usize) % browsers.len();
     browsers[index]
 }
 
-fn get_random_mobile_emulation() -> WreqEmulation {
+fn get_random_mobile_emulation(rng: &mut EmulationRng) -> Profile {
     let browsers = [
-        WreqEmulation::SafariIos17_4_1,
-        WreqEmulation::SafariIos17_2,
-        WreqEmulation::SafariIos16_5,
-        WreqEmulation::FirefoxAndroid135,
+        Profile::SafariIos17_4_1,
+        Profile::SafariIos17_2,
+        Profile::SafariIos16_5,
+        Profile::FirefoxAndroid135,
     ];
 
-    let index = (fast_random() as usize) % browsers.len();
+    let index = (rng.next() as usize) % browsers.len();
     browsers[index]
 }
 
-fn get_random_emulation() -> WreqEmulation {
-    if fast_random() % 100 < 50 {
-        get_random_desktop_emulation()
+fn get_random_emulation(rng: &mut EmulationRng) -> Profile {
+    if rng.next() % 100 < 50 {
+        get_random_desktop_emulation(rng)
     } else {
-        get_random_mobile_emulation()
+        get_random_mobile_emulation(rng)
     }
 }
 
-fn wreq_error_to_magnus_error(err: WreqError) -> MagnusError {
-    MagnusError::new(
-        exception::runtime_error(),
-        format!("HTTP request failed: {}", err),
-    )
-}
-
 fn normalize_header_name(name: &str) -> String {
     name.replace('_', "-")
         .split('-')
@@ -117,17 +216,97 @@ fn get_runtime() -> Result<Arc<Runtime>, MagnusError> {
     Ok(Arc::clone(&RUNTIME))
 }
 
+#[derive(Default)]
 struct RequestOptions {
-    body: Option<String>,
+    body: Option<Bytes>,
+    // Set instead of `body` for forms with file parts
+    multipart: Option<multipart::Form>,
+    // Set instead of `body` for IO and Enumerator bodies
+    upload: Option<upload::Upload>,
     content_type: Option<String>,
+    // Leave the body on the connection and hand it out chunk by chunk
+    stream: bool,
+    // Overrides for the client's body limits
+    limits: BodyLimits,
+    // Overrides the client's `compress`; `Some(None)` turns it off
+    compress: Option<Option<Compression>>,
+    // Merged over the client's headers for this request only
+    headers: HashMap<String, String>,
+    // Overrides of the client's settings for this request only
+    timeout: Option<f64>,
+    redirect_policy: Option<Policy>,
+    proxy: Option<wreq::Proxy>,
+}
+
+/// Keys understood in a request's options hash.
+const REQUEST_OPTION_KEYS: &[&str] = &[
+    "json",
+    "form",
+    "body",
+    "params",
+    "array_style",
+    "stream",
+    "max_body_size",
+    "max_decompressed_size",
+    "max_compression_ratio",
+    "compress",
+    "headers",
+    "timeout",
+    "follow",
+    "proxy",
+    "auth",
+];
+
+/// Raises ArgumentError for a key that isn't a request option, rather than
+/// silently ignoring a typo.
+fn check_option_keys(opts_hash: RHash) -> Result<(), MagnusError> {
+    opts_hash.foreach(|key: Value, _value: Value| {
+        let known = match Symbol::from_value(key) {
+            Some(sym) => REQUEST_OPTION_KEYS.contains(&&*sym.name()?),
+            None => false,
+        };
+        if !known {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                format!("unknown keyword: {}", key.inspect()),
+            ));
+        }
+        Ok(ForEach::Continue)
+    })
+}
+
+/// Reads the per-request `headers:`, `auth:`, `timeout:`, `follow:` and
+/// `proxy:` overrides.
+fn extract_overrides(opts_hash: RHash, options: &mut RequestOptions) -> Result<(), MagnusError> {
+    if let Some(headers_val) = opts_hash.get(Symbol::new("headers")) {
+        RHash::try_convert(headers_val)?.foreach(|key: Value, value: Value| {
+            let name = normalize_header_name(&params::key_string(key)?);
+            options.headers.insert(name, String::try_convert(value)?);
+            Ok(ForEach::Continue)
+        })?;
+    }
+    if let Some(auth_val) = opts_hash.get(Symbol::new("auth")) {
+        options.headers.insert("Authorization".to_string(), String::try_convert(auth_val)?);
+    }
+    if let Some(timeout_val) = opts_hash.get(Symbol::new("timeout")) {
+        options.timeout = Some(f64::try_convert(timeout_val)?);
+    }
+    if let Some(follow_val) = opts_hash.get(Symbol::new("follow")) {
+        options.redirect_policy = Some(redirect_policy(follow_val)?);
+    }
+    if let Some(proxy_val) = opts_hash.get(Symbol::new("proxy")) {
+        let proxy = String::try_convert(proxy_val)?;
+        options.proxy = Some(wreq::Proxy::all(&proxy).map_err(|e| {
+            MagnusError::new(exception::arg_error(), format!("Invalid proxy URL: {}", e))
+        })?);
+    }
+    Ok(())
 }
 
 fn extract_options(args: &[Value]) -> Result<RequestOptions, MagnusError> {
+    let mut options = RequestOptions::default();
     if args.len() <= 1 {
-        return Ok(RequestOptions {
-            body: None,
-            content_type: None,
-        });
+        return Ok(options);
     }
 
     let opts_value = &args[1];
@@ -135,64 +314,80 @@ fn extract_options(args: &[Value]) -> Result<RequestOptions, MagnusError> {
         let json_key = Symbol::new("json").into_value();
         let form_key = Symbol::new("form").into_value();
         let body_key = Symbol::new("body").into_value();
+        let stream_key = Symbol::new("stream").into_value();
+
+        check_option_keys(opts_hash)?;
+        extract_overrides(opts_hash, &mut options)?;
+        if let Some(stream_val) = opts_hash.get(stream_key) {
+            options.stream = stream_val.to_bool();
+        }
+        options.limits = BodyLimits::from_hash(opts_hash)?;
+        if let Some(compress_val) = opts_hash.get(Symbol::new("compress")) {
+            options.compress = Some(Compression::from_value(compress_val)?);
+        }
         
         if let Some(json_val) = opts_hash.get(json_key) {
-            let json_str = magnus::eval::<String>(&format!(
-                "require 'json'; JSON.generate({})",
-                json_val
-            ))?;
-            return Ok(RequestOptions {
-                body: Some(json_str),
-                content_type: Some("application/json".to_string()),
-            });
+            let ruby = Ruby::get_with(json_val);
+            options.body = Some(Bytes::from(json::generate(&ruby, json_val)?));
+            options.content_type = Some("application/json".to_string());
+            return Ok(options);
         }
         
         if let Some(form_val) = opts_hash.get(form_key) {
             if let Ok(form_hash) = RHash::try_convert(form_val) {
-                let mut pairs = Vec::new();
-                form_hash.foreach(|key: Value, value: Value| {
-                    let key_str = if let Some(sym) = Symbol::from_value(key) {
-                        sym.name()?.to_string()
-                    } else {
-                        String::try_convert(key)?
-                    };
-                    let val_str = if let Some(sym) = Symbol::from_value(value) {
-                        sym.name()?.to_string()
-                    } else {
-                        String::try_convert(value)?
-                    };
-                    pairs.push(format!("{}={}", 
-                        urlencoding::encode(&key_str),
-                        urlencoding::encode(&val_str)
-                    ));
-                    Ok(magnus::r_hash::ForEach::Continue)
-                }).ok();
-                
-                return Ok(RequestOptions {
-                    body: Some(pairs.join("&")),
-                    content_type: Some("application/x-www-form-urlencoded".to_string()),
-                });
+                let style = ArrayStyle::from_hash(opts_hash)?;
+                if let Some(form) = multipart::Form::from_hash(form_hash, style)? {
+                    options.content_type = Some(form.content_type());
+                    options.multipart = Some(form);
+                    return Ok(options);
+                }
+
+                let pairs = params::flatten(form_hash, style)?;
+                options.body = Some(Bytes::from(params::form_body(&pairs)));
+                options.content_type = Some("application/x-www-form-urlencoded".to_string());
+                return Ok(options);
             }
         }
         
         if let Some(body_val) = opts_hash.get(body_key) {
-            if let Ok(body_str) = String::try_convert(body_val) {
-                return Ok(RequestOptions {
-                    body: Some(body_str),
-                    content_type: Some("text/plain; charset=utf-8".to_string()),
-                });
+            if RString::from_value(body_val).is_none() {
+                if let Some(upload) = upload::Upload::from_value(body_val)? {
+                    options.upload = Some(upload);
+                    return Ok(options);
+                }
+            }
+            if let Ok(body_str) = RString::try_convert(body_val) {
+                options.body = Some(string_bytes(body_str));
+                options.content_type = Some(default_content_type(body_str));
+                return Ok(options);
             }
         }
         
-        Ok(RequestOptions {
-            body: None,
-            content_type: None,
-        })
+        Ok(options)
     } else {
-        Ok(RequestOptions {
-            body: Some(String::try_convert(*opts_value)?),
-            content_type: None,
-        })
+        options.body = Some(string_bytes(RString::try_convert(*opts_value)?));
+        Ok(options)
+    }
+}
+
+/// The string's bytes as they are: binary bodies are never UTF-8 validated.
+fn string_bytes(string: RString) -> Bytes {
+    // SAFETY: the slice is copied before Ruby can run again
+    Bytes::copy_from_slice(unsafe { string.as_slice() })
+}
+
+/// Content-Type for a raw `body:` string when the caller didn't set one: binary
+/// (ASCII-8BIT) strings are `application/octet-stream`, anything else is text in
+/// the string's own encoding.
+fn default_content_type(string: RString) -> String {
+    let ruby = Ruby::get_with(string);
+    let index = string.enc_get();
+    if index == ruby.ascii8bit_encindex() {
+        "application/octet-stream".to_string()
+    } else if index == ruby.utf8_encindex() || index == ruby.usascii_encindex() {
+        "text/plain; charset=utf-8".to_string()
+    } else {
+        format!("text/plain; charset={}", RbEncoding::from(index).name())
     }
 }
 
@@ -209,24 +404,8 @@ fn apply_params_to_url(url_str: &str, args: &[Value]) -> Result<String, MagnusEr
                     MagnusError::new(exception::arg_error(), format!("Invalid URL: {}", e))
                 })?;
                 
-                {
-                    let mut query_pairs = url.query_pairs_mut();
-                    params_hash.foreach(|key: Value, value: Value| {
-                        let key_str = if let Some(sym) = Symbol::from_value(key) {
-                            sym.name()?.to_string()
-                        } else {
-                            String::try_convert(key)?
-                        };
-                        let val_str = if let Some(sym) = Symbol::from_value(value) {
-                            sym.name()?.to_string()
-                        } else {
-                            String::try_convert(value)?
-                        };
-                        query_pairs.append_pair(&key_str, &val_str);
-                        Ok(magnus::r_hash::ForEach::Continue)
-                    }).ok();
-                }
-                
+                let pairs = params::flatten(params_hash, ArrayStyle::from_hash(opts_hash)?)?;
+                params::append_query(&mut url, &pairs);
                 return Ok(url.to_string());
             }
         }
@@ -235,7 +414,29 @@ fn apply_params_to_url(url_str: &str, args: &[Value]) -> Result<String, MagnusEr
     Ok(url_str.to_string())
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Redirect policy for a `follow` value: true, false or `{max_hops: n}`.
+fn redirect_policy(arg: Value) -> Result<Policy, MagnusError> {
+    if let Ok(bool_val) = bool::try_convert(arg) {
+        if bool_val {
+            Ok(Policy::limited(10))
+        } else {
+            Ok(Policy::none())
+        }
+    } else if let Ok(hash) = RHash::try_convert(arg) {
+        let max_hops_key = Symbol::new("max_hops").into_value();
+        match hash.get(max_hops_key) {
+            Some(max_hops_val) => Ok(Policy::limited(usize::try_convert(max_hops_val)?)),
+            None => Ok(Policy::limited(10)),
+        }
+    } else {
+        Err(MagnusError::new(
+            exception::arg_error(),
+            "follow() requires bool or hash with :max_hops"
+        ))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 enum HttpMethod {
     Get,
     Post,
@@ -244,8 +445,59 @@ enum HttpMethod {
     Head,
     Patch,
     Options,
+    /// Any other method: PROPFIND, PURGE, QUERY... Upper-cased.
+    Other(wreq::Method),
+}
+
+impl HttpMethod {
+    /// Parses a verb the way http.rb does, upper-casing it: `:propfind` is
+    /// PROPFIND. Anything that is a valid RFC 9110 token is accepted.
+    fn parse(verb: &str) -> Result<Self, MagnusError> {
+        let verb = verb.to_ascii_uppercase();
+        Ok(match verb.as_str() {
+            "GET" => HttpMethod::Get,
+            "POST" => HttpMethod::Post,
+            "PUT" => HttpMethod::Put,
+            "DELETE" => HttpMethod::Delete,
+            "HEAD" => HttpMethod::Head,
+            "PATCH" => HttpMethod::Patch,
+            "OPTIONS" => HttpMethod::Options,
+            _ if is_token(&verb) => HttpMethod::Other(
+                wreq::Method::from_bytes(verb.as_bytes())
+                    .map_err(|e| MagnusError::new(exception::arg_error(), e.to_string()))?,
+            ),
+            _ => {
+                return Err(MagnusError::new(
+                    exception::arg_error(),
+                    format!("Invalid HTTP verb: {:?}", verb),
+                ))
+            }
+        })
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Options => "OPTIONS",
+            HttpMethod::Other(method) => method.as_str(),
+        }
+    }
+}
+
+/// RFC 9110 `token`: one or more tchars.
+fn is_token(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_request(
     client: &wreq::Client,
     method: HttpMethod,
@@ -254,12 +506,16 @@ fn execute_request(
     user_agent: &Option<String>,
     redirect_policy: &Option<Policy>,
     timeout: f64,
-    body: Option<String>,
-    content_type: Option<String>,
+    mut options: RequestOptions,
+    encoding: Option<String>,
+    limits: BodyLimits,
+    compress: Option<Compression>,
+    connections: &ConnectionTracker,
 ) -> Result<RbHttpResponse, MagnusError> {
     let runtime = get_runtime()?;
+    let limits = limits.merge(options.limits);
 
-    let mut request = match method {
+    let mut request = match &method {
         HttpMethod::Get => client.get(url),
         HttpMethod::Post => client.post(url),
         HttpMethod::Put => client.put(url),
@@ -267,6 +523,18 @@ fn execute_request(
         HttpMethod::Head => client.head(url),
         HttpMethod::Patch => client.patch(url),
         HttpMethod::Options => client.options(url),
+        HttpMethod::Other(method) => client.request(method.clone(), url),
+    };
+
+    // Per-request headers win over the client's
+    let merged_headers;
+    let headers = if options.headers.is_empty() {
+        headers
+    } else {
+        let mut merged = headers.clone();
+        merged.extend(std::mem::take(&mut options.headers));
+        merged_headers = merged;
+        &merged_headers
     };
 
     // Pre-allocate HeaderMap with capacity (headers + 3 defaults: accept, user-agent, content-type)
@@ -339,7 +607,7 @@ fn execute_request(
     }
 
     // Set content-type header if provided and not already set
-    if let Some(ct) = &content_type {
+    if let Some(ct) = &options.content_type {
         if !has_content_type {
             if let Ok(val) = HeaderValue::from_str(ct) {
                 header_map.insert(HeaderName::from_static("content-type"), val);
@@ -348,37 +616,160 @@ fn execute_request(
     }
     // Don't automatically set application/octet-stream - let the server handle defaults
 
+    // A Content-Encoding from the caller means the body is already encoded
+    let has_body = options.body.is_some() || options.multipart.is_some() || options.upload.is_some();
+    let compress = options
+        .compress
+        .unwrap_or(compress)
+        .filter(|_| has_body && !header_map.contains_key("content-encoding"));
+    if let Some(compression) = compress {
+        header_map.insert(
+            HeaderName::from_static("content-encoding"),
+            HeaderValue::from_static(compression.content_encoding()),
+        );
+    }
+
     request = request.headers(header_map);
 
-    if let Some(policy) = redirect_policy {
-        request = request.redirect(policy.clone());
+    if let Some(policy) = options.redirect_policy.as_ref().or(redirect_policy.as_ref()) {
+        request = request.redirect(history::traced(policy.clone()));
     }
 
+    let timeout = options.timeout.unwrap_or(timeout);
     if timeout > 0.0 {
         request = request.timeout(Duration::from_secs_f64(timeout));
     }
 
-    if let Some(body_str) = body {
-        request = request.body(body_str);
+    if let Some(proxy) = options.proxy {
+        request = request.proxy(proxy);
     }
 
-    // Release GVL during HTTP I/O (send + response read)
-    let result: Result<RbHttpResponse, String> = gvl::without_gvl(|| {
-        // Send HTTP request
-        let response = match runtime.block_on(request.send()) {
-            Ok(resp) => resp,
-            Err(e) => return Err(format!("HTTP request failed: {}", e)),
+    let mut pump = None;
+    // In-memory body still to be compressed, once the GVL is released
+    let mut to_compress = None;
+    if let Some(form) = options.multipart {
+        request = request.body(streamed_body(form.into_body(), compress)?);
+    } else if let Some(upload) = options.upload {
+        let (upload_pump, body) = upload.into_body();
+        request = request.body(streamed_body(body, compress)?);
+        pump = Some(upload_pump);
+    } else if let Some(body) = options.body {
+        match compress {
+            Some(compression) => to_compress = Some((compression, body)),
+            None => request = request.body(body),
+        }
+    }
+
+    let stream = options.stream;
+    let started_at = Instant::now();
+    let request_url = url.to_string();
+    // A streamed body keeps its own copy for the errors it raises later
+    let body_method = method.as_str().to_string();
+    let connections = connections.clone();
+    let trace = timings::shared_trace();
+
+    let future = async move {
+        // Compressed here rather than up front so a large JSON batch doesn't hold the GVL
+        let request = match to_compress {
+            Some((compression, body)) => request.body(compression.compress(&body).map_err(|e| {
+                RequestError::new(ErrorKind::Generic, format!("Failed to compress request body: {}", e))
+            })?),
+            None => request,
+        };
+
+        // Send HTTP request, then read and process the response (decompression happens here)
+        let response = match timings::track(Arc::clone(&trace), request.send()).await {
+            Ok(response) => response,
+            Err(e) => {
+                let redirects = timings::redirect_times(&trace).len();
+                return Err(RequestError::from_wreq(&e, "HTTP request failed").with_redirects(redirects));
+            }
+        };
+        let meta = ResponseMeta {
+            connection: ConnectionInfo::new(&response, &connections),
+            timings: RequestTimings::new(started_at, &trace),
+            history: history::hops(&response, started_at, &timings::redirect_times(&trace)),
         };
 
-        // Read and process response (decompression happens here)
-        match runtime.block_on(RbHttpResponse::new(response)) {
-            Ok(rb_response) => Ok(rb_response),
-            Err(e) => Err(e.to_string()),
+        if stream {
+            // Streamed bodies stay on the connection until Ruby reads them
+            RbHttpResponse::streaming(response, meta, encoding, limits, body_method, request_url)
+        } else {
+            RbHttpResponse::new(response, meta, encoding, limits).await
         }
-    });
+    };
 
-    // Convert String errors back to MagnusError after GVL re-acquired
-    result.map_err(|e| MagnusError::new(exception::runtime_error(), e))
+    // Release GVL during HTTP I/O (send + response read)
+    let result = match pump {
+        // Streamed request bodies take the GVL back briefly for each chunk
+        Some(pump) => pump.run(&runtime, future)?,
+        None => gvl::block_on_interruptible(&runtime, future),
+    };
+
+    let result = match result {
+        Some(result) => result,
+        None => {
+            // Raise whatever Ruby interrupted us with (Thread#raise, Timeout, Interrupt)
+            magnus::Ruby::get()
+                .expect("GVL re-acquired")
+                .thread_check_ints()?;
+            Err(RequestError::new(ErrorKind::Interrupted, "HTTP request interrupted"))
+        }
+    };
+
+    // Convert errors into Ruby exceptions after GVL re-acquired
+    result.map_err(|e| {
+        e.with_request(method.as_str(), url, started_at.elapsed())
+            .into_magnus()
+    })
+}
+
+/// Hands a streamed request body to wreq, compressed on the way out if asked to.
+fn streamed_body<B>(body: B, compress: Option<Compression>) -> Result<wreq::Body, MagnusError>
+where
+    B: http_body::Body<Data = Bytes> + Send + Sync + Unpin + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Ok(match compress {
+        Some(compression) => wreq::Body::wrap(compression.wrap(body).map_err(compression_error)?),
+        None => wreq::Body::wrap(body),
+    })
+}
+
+fn compression_error(err: std::io::Error) -> MagnusError {
+    MagnusError::new(
+        exception::runtime_error(),
+        format!("Failed to compress request body: {}", err),
+    )
+}
+
+fn emulation_of(profile: Profile) -> WreqEmulation {
+    WreqEmulation::builder().profile(profile).build()
+}
+
+/// Builds the underlying client. Responses are decompressed by us, not wreq.
+fn build_client(emulation: &WreqEmulation, proxy: Option<&str>) -> Result<wreq::Client, MagnusError> {
+    let mut builder = timings::instrument(wreq::Client::builder().emulation(emulation.clone()))
+        .no_gzip()
+        .no_brotli()
+        .no_zstd()
+        .no_deflate();
+
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(wreq::Proxy::all(proxy).map_err(|e| {
+            MagnusError::new(
+                exception::runtime_error(),
+                format!("Invalid proxy URL: {}", e),
+            )
+        })?);
+    }
+
+    builder.build().map_err(|e| {
+        MagnusError::new(
+            exception::runtime_error(),
+            format!("Failed to create client: {}", e),
+        )
+    })
 }
 
 #[magnus::wrap(class = "Wreq::HTTP::Client")]
@@ -399,6 +790,8 @@ impl Clone for ClientWrap {
 #[magnus::wrap(class = "Wreq::HTTP::Client")]
 struct RbHttpClient {
     client: ClientWrap,
+    // What `client` was built to look like, kept to rebuild it with a proxy
+    emulation: WreqEmulation,
     headers: HashMap<String, String>,
     user_agent: Option<String>,
     redirect_policy: Option<Policy>,
@@ -409,90 +802,74 @@ struct RbHttpClient {
     auth_header: Option<String>,
     accept_type: Option<String>,
     encoding: Option<String>,
+    limits: BodyLimits,
+    // Content-Encoding for request bodies
+    compress: Option<Compression>,
+    // Shared by clones, which share the connection pool too
+    connections: ConnectionTracker,
     base_url: Option<String>,
     closed: AtomicBool,
 }
 
 impl RbHttpClient {
     fn new() -> Result<Self, MagnusError> {
-        let client = wreq::Client::builder()
-            .emulation(get_random_emulation())
-            .no_gzip()
-            .no_brotli()
-            .no_zstd()
-            .no_deflate()
-            .build()
-            .map_err(|e| {
-                MagnusError::new(
-                    exception::runtime_error(),
-                    format!("Failed to create client: {}", e),
-                )
-            })?;
+        Self::with_emulation(emulation_of(get_random_emulation(&mut EmulationRng::next_client())))
+    }
 
-        Ok(Self {
-            client: ClientWrap(client),
-            headers: HashMap::new(),
-            user_agent: None,
-            redirect_policy: Some(Policy::limited(10)),
-            timeout: 0.0,
-            proxy: None,
-            cookies: None,
-            auth_header: None,
-            accept_type: None,
-            encoding: None,
-            base_url: None,
-            closed: AtomicBool::new(false),
-        })
+    /// `Client.new_desktop`, optionally with `seed: 42`.
+    fn new_desktop(args: &[Value]) -> Result<Self, MagnusError> {
+        let mut rng = EmulationRng::from_args(args)?;
+        Self::with_emulation(emulation_of(get_random_desktop_emulation(&mut rng)))
     }
 
-    fn new_desktop() -> Result<Self, MagnusError> {
-        let client = wreq::Client::builder()
-            .emulation(get_random_desktop_emulation())
-            .no_gzip()
-            .no_brotli()
-            .no_zstd()
-            .no_deflate()
-            .build()
-            .map_err(|e| {
-                MagnusError::new(
-                    exception::runtime_error(),
-                    format!("Failed to create client: {}", e),
-                )
-            })?;
+    /// `Client.new_mobile`, optionally with `seed: 42`.
+    fn new_mobile(args: &[Value]) -> Result<Self, MagnusError> {
+        let mut rng = EmulationRng::from_args(args)?;
+        Self::with_emulation(emulation_of(get_random_mobile_emulation(&mut rng)))
+    }
 
-        Ok(Self {
-            client: ClientWrap(client),
-            headers: HashMap::new(),
-            user_agent: None,
-            redirect_policy: Some(Policy::limited(10)),
-            timeout: 0.0,
-            proxy: None,
-            cookies: None,
-            auth_header: None,
-            accept_type: None,
-            encoding: None,
-            base_url: None,
-            closed: AtomicBool::new(false),
-        })
+    /// `Client.new`, optionally with `emulation: :chrome_134` and the same
+    /// `os:`, `skip_headers:` and `skip_http2:` options as `emulate`, or with
+    /// `seed: 42` to pick the browser reproducibly. The two don't mix: a seed
+    /// only picks among browsers, `emulation:` names one.
+    fn rb_new(args: &[Value]) -> Result<Self, MagnusError> {
+        let Some(&opts) = args.first() else {
+            return Self::new();
+        };
+        let opts_hash = RHash::try_convert(opts)?;
+        let mut profile = None;
+        let mut seed = None;
+        let mut options = emulation::Options::default();
+        opts_hash.foreach(|key: Symbol, value: Value| {
+            let key = key.name()?;
+            if key == "emulation" {
+                profile = Some(emulation::profile_from_value(value)?);
+            } else if key == "seed" {
+                seed = Some(u64::try_convert(value)?);
+            } else if !options.set(&key, value)? {
+                return Err(emulation::unknown_keyword(&key));
+            }
+            Ok(ForEach::Continue)
+        })?;
+        if profile.is_some() && seed.is_some() {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                "emulation: and seed: can't be combined",
+            ));
+        }
+        let profile = profile.unwrap_or_else(|| {
+            let mut rng = seed.map_or_else(EmulationRng::next_client, EmulationRng::from_seed);
+            get_random_emulation(&mut rng)
+        });
+        Self::with_emulation(options.build(profile))
     }
 
-    fn new_mobile() -> Result<Self, MagnusError> {
-        let client = wreq::Client::builder()
-            .emulation(get_random_mobile_emulation())
-            .no_gzip()
-            .no_brotli()
-            .no_zstd()
-            .no_deflate()
-            .build()
-            .map_err(|e| {
-                MagnusError::new(
-                    exception::runtime_error(),
-                    format!("Failed to create client: {}", e),
-                )
-            })?;
+    fn with_emulation(emulation: WreqEmulation) -> Result<Self, MagnusError> {
+        let client = build_client(&emulation, None)?;
 
         Ok(Self {
             client: ClientWrap(client),
+            emulation,
             headers: HashMap::new(),
             user_agent: None,
             redirect_policy: Some(Policy::limited(10)),
@@ -502,6 +879,9 @@ impl RbHttpClient {
             auth_header: None,
             accept_type: None,
             encoding: None,
+            limits: BodyLimits::default(),
+            compress: None,
+            connections: ConnectionTracker::default(),
             base_url: None,
             closed: AtomicBool::new(false),
         })
@@ -558,62 +938,31 @@ impl RbHttpClient {
 
     fn with_proxy(&self, proxy: String) -> Result<Self, MagnusError> {
         let mut new_client = self.clone();
-        new_client.proxy = Some(proxy.clone());
-
-        let client = wreq::Client::builder()
-            .emulation(get_random_emulation())
-            .no_gzip()
-            .no_brotli()
-            .no_zstd()
-            .no_deflate()
-            .proxy(wreq::Proxy::all(&proxy).map_err(|e| {
-                MagnusError::new(
-                    exception::runtime_error(),
-                    format!("Invalid proxy URL: {}", e),
-                )
-            })?)
-            .build()
-            .map_err(|e| {
-                MagnusError::new(
-                    exception::runtime_error(),
-                    format!("Failed to create client with proxy: {}", e),
-                )
-            })?;
+        new_client.client = ClientWrap(build_client(&self.emulation, Some(&proxy))?);
+        new_client.proxy = Some(proxy);
+        new_client.connections = ConnectionTracker::default();
 
-        new_client.client = ClientWrap(client);
+        Ok(new_client)
+    }
+
+    /// A client that looks like one exact browser, e.g. `emulate(:chrome_134)`,
+    /// optionally with `os:`, `skip_headers:` and `skip_http2:`.
+    fn emulate(&self, args: &[Value]) -> Result<Self, MagnusError> {
+        let emulation = emulation::from_args(args)?;
+        let mut new_client = self.clone();
+        new_client.client = ClientWrap(build_client(&emulation, self.proxy.as_deref())?);
+        new_client.emulation = emulation;
+        new_client.connections = ConnectionTracker::default();
 
         Ok(new_client)
     }
 
     fn follow(&self, args: &[Value]) -> Result<Self, MagnusError> {
         let mut new_client = self.clone();
-        
-        if args.is_empty() {
-            new_client.redirect_policy = Some(Policy::limited(10));
-        } else {
-            let arg = args[0];
-            if let Some(bool_val) = bool::try_convert(arg).ok() {
-                if bool_val {
-                    new_client.redirect_policy = Some(Policy::limited(10));
-                } else {
-                    new_client.redirect_policy = Some(Policy::none());
-                }
-            } else if let Ok(hash) = RHash::try_convert(arg) {
-                let max_hops_key = Symbol::new("max_hops").into_value();
-                if let Some(max_hops_val) = hash.get(max_hops_key) {
-                    let max_hops = usize::try_convert(max_hops_val)?;
-                    new_client.redirect_policy = Some(Policy::limited(max_hops));
-                } else {
-                    new_client.redirect_policy = Some(Policy::limited(10));
-                }
-            } else {
-                return Err(MagnusError::new(
-                    exception::arg_error(),
-                    "follow() requires bool or hash with :max_hops"
-                ));
-            }
-        }
-        
+        new_client.redirect_policy = Some(match args.first() {
+            Some(&arg) => redirect_policy(arg)?,
+            None => Policy::limited(10),
+        });
         Ok(new_client)
     }
 
@@ -735,6 +1084,18 @@ impl RbHttpClient {
         new_client
     }
 
+    fn limits(&self, limits_hash: RHash) -> Result<Self, MagnusError> {
+        let mut new_client = self.clone();
+        new_client.limits = self.limits.merge(BodyLimits::from_hash(limits_hash)?);
+        Ok(new_client)
+    }
+
+    fn compress(&self, value: Value) -> Result<Self, MagnusError> {
+        let mut new_client = self.clone();
+        new_client.compress = Compression::from_value(value)?;
+        Ok(new_client)
+    }
+
     fn get(&self, args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
         self.ensure_open()?;
         let url_str = String::try_convert(args[0])?;
@@ -750,8 +1111,11 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
@@ -770,8 +1134,11 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
@@ -790,8 +1157,11 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
@@ -810,8 +1180,11 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
@@ -830,8 +1203,11 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
@@ -850,26 +1226,40 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
+        )
+    }
+
+    fn options(&self, args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
+        self.ensure_open()?;
+        let url_str = String::try_convert(args[0])?;
+        let resolved_url = self.resolve_url(&url_str)?;
+        let url = apply_params_to_url(&resolved_url, args)?;
+        let opts = extract_options(args)?;
+
+        execute_request(
+            self.client.inner(),
+            HttpMethod::Options,
+            &url,
+            &self.headers,
+            &self.user_agent,
+            &self.redirect_policy,
+            self.timeout,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
     fn request(&self, args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
         self.ensure_open()?;
-        let verb = Symbol::try_convert(args[0])?;
-        let verb_name = verb.name()?;
-        let verb_str: &str = &verb_name;
-        let method = match verb_str {
-            "get" => HttpMethod::Get,
-            "post" => HttpMethod::Post,
-            "put" => HttpMethod::Put,
-            "delete" => HttpMethod::Delete,
-            "head" => HttpMethod::Head,
-            "patch" => HttpMethod::Patch,
-            "options" => HttpMethod::Options,
-            _ => return Err(MagnusError::new(exception::arg_error(), "Invalid HTTP verb")),
-        };
+        let method = HttpMethod::parse(&params::key_string(args[0])?)?;
         
         let url_str = String::try_convert(args[1])?;
         let resolved_url = self.resolve_url(&url_str)?;
@@ -884,8 +1274,11 @@ impl RbHttpClient {
             &self.user_agent,
             &self.redirect_policy,
             self.timeout,
-            opts.body,
-            opts.content_type,
+            opts,
+            self.encoding.clone(),
+            self.limits,
+            self.compress,
+            &self.connections,
         )
     }
 
@@ -912,6 +1305,7 @@ impl Clone for RbHttpClient {
     fn clone(&self) -> Self {
         Self {
             client: self.client.clone(),
+            emulation: self.emulation.clone(),
             headers: self.headers.clone(),
             user_agent: self.user_agent.clone(),
             redirect_policy: self.redirect_policy.clone(),
@@ -921,108 +1315,238 @@ impl Clone for RbHttpClient {
             auth_header: self.auth_header.clone(),
             accept_type: self.accept_type.clone(),
             encoding: self.encoding.clone(),
+            limits: self.limits,
+            compress: self.compress,
+            connections: self.connections.clone(),
             base_url: self.base_url.clone(),
             closed: AtomicBool::new(self.closed.load(Ordering::Relaxed)),
         }
     }
 }
 
+/// What's known about a response once its headers are in, before the body is read.
+struct ResponseMeta {
+    connection: ConnectionInfo,
+    timings: RequestTimings,
+    // Redirects followed on the way, oldest first
+    history: Vec<Hop>,
+}
+
 struct ResponseData {
     status: u16,
-    headers: HashMap<String, String>,
-    body: Option<String>,
+    headers: Arc<HeaderMap>,
+    body: Bytes,
     url: String,
     content_length: u64,
+    connection: ConnectionInfo,
+    timings: RequestTimings,
+    history: Vec<Hop>,
+    // Charset forced with `encoding()`, takes precedence over the response's own Content-Type charset
+    encoding: Option<String>,
 }
 
 #[magnus::wrap(class = "Wreq::HTTP::Response")]
 struct RbHttpResponse {
     data: Arc<ResponseData>,
+    // Set for `stream: true` requests; `data.body` stays empty
+    stream: Option<RbBody>,
 }
 
 impl RbHttpResponse {
-    async fn new(response: WreqResponse) -> Result<Self, MagnusError> {
+    async fn new(
+        mut response: WreqResponse,
+        meta: ResponseMeta,
+        encoding: Option<String>,
+        limits: BodyLimits,
+    ) -> Result<Self, RequestError> {
         let status = response.status().as_u16();
         let url = response.uri().to_string();
+        let headers = Arc::new(response.headers().clone());
 
-        // Read Content-Encoding header BEFORE consuming response
-        let encoding = response.headers()
-            .get("content-encoding")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string());
-
-        let mut headers = HashMap::new();
-        for (name, value) in response.headers().iter() {
-            if let Ok(value_str) = value.to_str() {
-                headers.insert(name.to_string(), value_str.to_string());
-            }
+        // Decompresses each chunk as it arrives (auto-decompression is disabled on the client)
+        let mut reader = ChunkReader::new(&response, limits)?;
+        let mut body = BytesMut::new();
+        while let Some(chunk) = reader.next(&mut response).await? {
+            body.extend_from_slice(&chunk);
         }
-
-        // Get raw bytes (compressed when auto-decompression disabled)
-        let raw_bytes = response.bytes().await.map_err(|e| {
-            MagnusError::new(
-                exception::runtime_error(),
-                format!("Failed to read response body: {}", e),
-            )
-        })?;
-
-        let content_length = raw_bytes.len() as u64;
-
-        // Decompress if Content-Encoding present
-        let body = if let Some(encoding_str) = encoding {
-            if encoding_str.trim().is_empty() || encoding_str.eq_ignore_ascii_case("identity") {
-                // Identity = no encoding
-                String::from_utf8_lossy(&raw_bytes).into_owned()
-            } else {
-                // Compressed response — decompress
-                let decompressed = decompress::decompress(&raw_bytes, &encoding_str)
-                    .map_err(|e| MagnusError::new(
-                        exception::runtime_error(),
-                        format!("Decompression failed: {}", e),
-                    ))?;
-                String::from_utf8_lossy(&decompressed).into_owned()
-            }
-        } else {
-            // No Content-Encoding header
-            String::from_utf8_lossy(&raw_bytes).into_owned()
-        };
+        let body = body.freeze();
+        let content_length = reader.wire_bytes();
+        let mut timings = meta.timings;
+        timings.finished_at = Some(Instant::now());
 
         Ok(Self {
             data: Arc::new(ResponseData {
                 status,
                 headers,
-                body: Some(body),
+                body,
                 url,
                 content_length,
+                connection: meta.connection,
+                timings,
+                history: meta.history,
+                encoding,
             }),
+            stream: None,
         })
     }
 
+    /// Wraps a response whose body hasn't been read yet.
+    fn streaming(
+        response: WreqResponse,
+        meta: ResponseMeta,
+        encoding: Option<String>,
+        limits: BodyLimits,
+        method: String,
+        request_url: String,
+    ) -> Result<Self, RequestError> {
+        let started_at = meta.timings.started_at;
+        let data = ResponseData {
+            status: response.status().as_u16(),
+            headers: Arc::new(response.headers().clone()),
+            body: Bytes::new(),
+            url: response.uri().to_string(),
+            content_length: 0,
+            connection: meta.connection,
+            timings: meta.timings,
+            history: meta.history,
+            encoding,
+        };
+
+        Ok(Self {
+            data: Arc::new(data),
+            stream: Some(RbBody::new(response, limits, method, request_url, started_at)?),
+        })
+    }
+
+    /// Whole body, reading the rest of a streamed one first.
+    fn body_bytes(&self) -> Result<Bytes, MagnusError> {
+        match &self.stream {
+            Some(stream) => stream.contents(),
+            None => Ok(self.data.body.clone()),
+        }
+    }
+
     fn status(&self) -> u16 {
         self.data.status
     }
 
-    fn body(&self) -> String {
-        match &self.data.body {
-            Some(body) => body.clone(),
-            None => String::new(),
+    /// Raw body bytes as an ASCII-8BIT string, or a `Response::Body` to read from
+    /// when the request was made with `stream: true`.
+    fn body(ruby: &Ruby, rb_self: &Self) -> Value {
+        match &rb_self.stream {
+            Some(stream) => stream.clone().into_value_with(ruby),
+            None => RString::from_slice(&rb_self.data.body).as_value(),
+        }
+    }
+
+    /// Body transcoded to UTF-8 from its detected charset, with invalid sequences replaced.
+    fn text(&self) -> Result<String, MagnusError> {
+        Ok(charset::decode(
+            &self.body_bytes()?,
+            self.data.encoding.as_deref(),
+            self.data.headers.get("content-type").and_then(|v| v.to_str().ok()),
+        ))
+    }
+
+    /// Parses the body as JSON straight into Ruby objects. Takes `symbolize_names:`
+    /// and `max_nesting:` like `JSON.parse`. Bodies that aren't UTF-8 are
+    /// transcoded from their charset first.
+    fn json(ruby: &Ruby, rb_self: &Self, args: &[Value]) -> Result<Value, MagnusError> {
+        let options = json::ParseOptions::from_args(args)?;
+        let body = rb_self.body_bytes()?;
+        match std::str::from_utf8(&body) {
+            Ok(_) => json::parse(ruby, &body, options),
+            Err(_) => json::parse(ruby, rb_self.text()?.as_bytes(), options),
+        }
+    }
+
+    /// Writes the body to `path` and returns the number of bytes written. A
+    /// streamed body goes from the connection to disk without touching the Ruby heap.
+    fn save_to(&self, path: String) -> Result<u64, MagnusError> {
+        let path = std::path::Path::new(&path);
+        match &self.stream {
+            Some(stream) => stream.save_to(path),
+            None => body::save_bytes(path, &self.data.body),
         }
     }
 
-    fn content_length(&self) -> u64 {
-        self.data.content_length
+    /// Suggested file name: Content-Disposition, else the last segment of the URL.
+    fn filename(&self) -> Option<String> {
+        let from_header = self.data.headers
+            .get("content-disposition")
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+            .and_then(|v| headers::disposition_filename(&v));
+        from_header.or_else(|| {
+            let url = Url::parse(&self.data.url).ok()?;
+            let segment = url.path_segments()?.next_back()?;
+            headers::sanitize_filename(&urlencoding::decode(segment).ok()?)
+        })
+    }
+
+    /// Bytes received before decompression. A streamed body hasn't been read yet,
+    /// so it reports the Content-Length header instead, nil when there is none.
+    fn content_length(&self) -> Option<u64> {
+        match &self.stream {
+            Some(_) => self.data.headers
+                .get("content-length")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok()),
+            None => Some(self.data.content_length),
+        }
+    }
+
+    fn to_s(&self) -> Result<RString, MagnusError> {
+        Ok(RString::from_slice(&self.body_bytes()?))
     }
 
-    fn to_s(&self) -> String {
-        self.body()
+    /// Negotiated HTTP version, `"1.1"` or `"2"`.
+    fn version(&self) -> &'static str {
+        self.data.connection.version_str()
     }
 
-    fn headers(&self) -> HashMap<String, String> {
-        self.data.headers.clone()
+    /// `"ip:port"` of the peer the response came from (the proxy, when one is used).
+    fn remote_addr(&self) -> Option<String> {
+        self.data.connection.remote_addr.map(|addr| addr.to_string())
+    }
+
+    fn local_addr(&self) -> Option<String> {
+        self.data.connection.local_addr.map(|addr| addr.to_string())
+    }
+
+    /// Where the time went: DNS, connect, TLS, waiting for headers, body download.
+    fn timings(&self) -> RbTimings {
+        let mut timings = self.data.timings;
+        if let Some(stream) = &self.stream {
+            timings.finished_at = stream.finished_at();
+        }
+        RbTimings::new(timings)
+    }
+
+    /// Redirects followed before this response, oldest first.
+    fn history(&self) -> Vec<RbRedirect> {
+        self.data.history.iter().cloned().map(RbRedirect::new).collect()
+    }
+
+    fn is_redirected(&self) -> bool {
+        !self.data.history.is_empty()
+    }
+
+    /// Whether this client already got a response over the same local/remote
+    /// address pair. A heuristic for "came out of the pool": wreq doesn't expose
+    /// whether a connection was reused.
+    fn connection_seen_before(&self) -> bool {
+        self.data.connection.seen_before
+    }
+
+    fn headers(&self) -> RbHeaders {
+        RbHeaders::new(Arc::clone(&self.data.headers))
     }
 
     fn content_type(&self) -> Option<String> {
-        self.data.headers.get("content-type").cloned()
+        self.data.headers
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
     }
 
     fn uri(&self) -> String {
@@ -1034,26 +1558,8 @@ impl RbHttpResponse {
     }
 
     fn charset(&self) -> Option<String> {
-        if let Some(content_type) = self.content_type() {
-            if let Some(charset_part) = content_type
-                .split(';')
-                .skip(1)
-                .find(|part| part.trim().to_lowercase().starts_with("charset="))
-            {
-                let charset = charset_part
-                    .trim()
-                    .split('=')
-                    .nth(1)
-                    .unwrap_or("")
-                    .trim()
-                    .to_string();
-
-                if !charset.is_empty() {
-                    return Some(charset);
-                }
-            }
-        }
-        None
+        self.content_type()
+            .and_then(|content_type| charset::charset_param(&content_type).map(str::to_string))
     }
 }
 
@@ -1062,12 +1568,29 @@ fn rb_get(args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
     client.get(args)
 }
 
-fn rb_desktop() -> Result<RbHttpClient, MagnusError> {
-    RbHttpClient::new_desktop()
+fn rb_desktop(args: &[Value]) -> Result<RbHttpClient, MagnusError> {
+    RbHttpClient::new_desktop(args)
+}
+
+fn rb_emulate(args: &[Value]) -> Result<RbHttpClient, MagnusError> {
+    RbHttpClient::with_emulation(emulation::from_args(args)?)
+}
+
+fn rb_set_random_seed(seed: Option<u64>) -> Option<u64> {
+    set_random_seed(seed);
+    seed
 }
 
-fn rb_mobile() -> Result<RbHttpClient, MagnusError> {
-    RbHttpClient::new_mobile()
+fn rb_random_seed() -> Option<u64> {
+    random_seed()
+}
+
+fn rb_emulations(ruby: &Ruby) -> RArray {
+    ruby.ary_from_iter(emulation::names().map(|name| ruby.to_symbol(name)))
+}
+
+fn rb_mobile(args: &[Value]) -> Result<RbHttpClient, MagnusError> {
+    RbHttpClient::new_mobile(args)
 }
 
 fn rb_post(args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
@@ -1095,6 +1618,11 @@ fn rb_patch(args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
     client.patch(args)
 }
 
+fn rb_options(args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
+    let client = RbHttpClient::new()?;
+    client.options(args)
+}
+
 fn rb_request(args: &[Value]) -> Result<RbHttpResponse, MagnusError> {
     let client = RbHttpClient::new()?;
     client.request(args)
@@ -1141,6 +1669,14 @@ fn rb_encoding(enc: String) -> Result<RbHttpClient, MagnusError> {
     Ok(RbHttpClient::new()?.encoding(enc))
 }
 
+fn rb_limits(limits_hash: RHash) -> Result<RbHttpClient, MagnusError> {
+    RbHttpClient::new()?.limits(limits_hash)
+}
+
+fn rb_compress(value: Value) -> Result<RbHttpClient, MagnusError> {
+    RbHttpClient::new()?.compress(value)
+}
+
 fn rb_accept(accept_value: Value) -> Result<RbHttpClient, MagnusError> {
     RbHttpClient::new()?.accept(accept_value)
 }
@@ -1149,51 +1685,101 @@ fn rb_accept(accept_value: Value) -> Result<RbHttpClient, MagnusError> {
 fn init(ruby: &magnus::Ruby) -> Result<(), MagnusError> {
     let wreq_module = ruby.define_module("Wreq")?;
     let http_module = wreq_module.define_module("HTTP")?;
+    error::define_exceptions(ruby)?;
 
     let response_class = http_module.define_class("Response", ruby.class_object())?;
     response_class.define_method("status", method!(RbHttpResponse::status, 0))?;
     response_class.define_method("body", method!(RbHttpResponse::body, 0))?;
     response_class.define_method("to_s", method!(RbHttpResponse::to_s, 0))?;
+    response_class.define_method("text", method!(RbHttpResponse::text, 0))?;
+    response_class.define_method("json", method!(RbHttpResponse::json, -1))?;
     response_class.define_method("headers", method!(RbHttpResponse::headers, 0))?;
     response_class.define_method("content_type", method!(RbHttpResponse::content_type, 0))?;
     response_class.define_method("uri", method!(RbHttpResponse::uri, 0))?;
     response_class.define_method("code", method!(RbHttpResponse::code, 0))?;
     response_class.define_method("charset", method!(RbHttpResponse::charset, 0))?;
     response_class.define_method("content_length", method!(RbHttpResponse::content_length, 0))?;
+    response_class.define_method("save_to", method!(RbHttpResponse::save_to, 1))?;
+    response_class.define_method("filename", method!(RbHttpResponse::filename, 0))?;
+    response_class.define_method("version", method!(RbHttpResponse::version, 0))?;
+    response_class.define_method("remote_addr", method!(RbHttpResponse::remote_addr, 0))?;
+    response_class.define_method("local_addr", method!(RbHttpResponse::local_addr, 0))?;
+    response_class.define_method("connection_seen_before?", method!(RbHttpResponse::connection_seen_before, 0))?;
+    response_class.define_method("timings", method!(RbHttpResponse::timings, 0))?;
+    response_class.define_method("history", method!(RbHttpResponse::history, 0))?;
+    response_class.define_method("redirected?", method!(RbHttpResponse::is_redirected, 0))?;
+
+    let redirect_class = http_module.define_class("Redirect", ruby.class_object())?;
+    redirect_class.define_method("status", method!(RbRedirect::status, 0))?;
+    redirect_class.define_method("uri", method!(RbRedirect::uri, 0))?;
+    redirect_class.define_method("location", method!(RbRedirect::location, 0))?;
+    redirect_class.define_method("headers", method!(RbRedirect::headers, 0))?;
+    redirect_class.define_method("elapsed", method!(RbRedirect::elapsed, 0))?;
+
+    let body_class = response_class.define_class("Body", ruby.class_object())?;
+    body_class.define_method("readpartial", method!(RbBody::readpartial, -1))?;
+    body_class.define_method("to_s", method!(RbBody::to_s, 0))?;
+
+    let timings_class = http_module.define_class("Timings", ruby.class_object())?;
+    timings_class.define_method("dns", method!(RbTimings::dns, 0))?;
+    timings_class.define_method("connect", method!(RbTimings::connect, 0))?;
+    timings_class.define_method("tls", method!(RbTimings::tls, 0))?;
+    timings_class.define_method("ttfb", method!(RbTimings::ttfb, 0))?;
+    timings_class.define_method("download", method!(RbTimings::download, 0))?;
+    timings_class.define_method("total", method!(RbTimings::total, 0))?;
+
+    let headers_class = http_module.define_class("Headers", ruby.class_object())?;
+    headers_class.define_method("[]", method!(RbHeaders::aref, 1))?;
+    headers_class.define_method("get", method!(RbHeaders::get, 1))?;
+    headers_class.define_method("to_a", method!(RbHeaders::to_a, 0))?;
+    headers_class.define_method("to_h", method!(RbHeaders::to_h, 0))?;
+    headers_class.define_method("keys", method!(RbHeaders::keys, 0))?;
+    headers_class.define_method("include?", method!(RbHeaders::include, 1))?;
+    headers_class.define_method("size", method!(RbHeaders::size, 0))?;
+    headers_class.define_method("empty?", method!(RbHeaders::is_empty, 0))?;
 
     let client_class = http_module.define_class("Client", ruby.class_object())?;
-    client_class.define_singleton_method("new", function!(RbHttpClient::new, 0))?;
-    client_class.define_singleton_method("new_desktop", function!(RbHttpClient::new_desktop, 0))?;
-    client_class.define_singleton_method("new_mobile", function!(RbHttpClient::new_mobile, 0))?;
+    client_class.define_singleton_method("new", function!(RbHttpClient::rb_new, -1))?;
+    client_class.define_singleton_method("new_desktop", function!(RbHttpClient::new_desktop, -1))?;
+    client_class.define_singleton_method("new_mobile", function!(RbHttpClient::new_mobile, -1))?;
     client_class.define_method("with_headers", method!(RbHttpClient::with_headers, 1))?;
     client_class.define_method("follow", method!(RbHttpClient::follow, -1))?;
     client_class.define_method("timeout", method!(RbHttpClient::timeout, 1))?;
     client_class.define_method("with_proxy", method!(RbHttpClient::with_proxy, 1))?;
+    client_class.define_method("emulate", method!(RbHttpClient::emulate, -1))?;
     client_class.define_method("via", method!(RbHttpClient::via, -1))?;
     client_class.define_method("cookies", method!(RbHttpClient::cookies, 1))?;
     client_class.define_method("basic_auth", method!(RbHttpClient::basic_auth, 1))?;
     client_class.define_method("auth", method!(RbHttpClient::auth, 1))?;
     client_class.define_method("accept", method!(RbHttpClient::accept, 1))?;
     client_class.define_method("encoding", method!(RbHttpClient::encoding, 1))?;
+    client_class.define_method("limits", method!(RbHttpClient::limits, 1))?;
+    client_class.define_method("compress", method!(RbHttpClient::compress, 1))?;
     client_class.define_method("get", method!(RbHttpClient::get, -1))?;
     client_class.define_method("post", method!(RbHttpClient::post, -1))?;
     client_class.define_method("put", method!(RbHttpClient::put, -1))?;
     client_class.define_method("delete", method!(RbHttpClient::delete, -1))?;
     client_class.define_method("head", method!(RbHttpClient::head, -1))?;
     client_class.define_method("patch", method!(RbHttpClient::patch, -1))?;
+    client_class.define_method("options", method!(RbHttpClient::options, -1))?;
     client_class.define_method("request", method!(RbHttpClient::request, -1))?;
     client_class.define_method("headers", method!(RbHttpClient::headers, 1))?;
     client_class.define_method("persistent", method!(RbHttpClient::persistent, -1))?;
     client_class.define_method("close", method!(RbHttpClient::close, 0))?;
 
     http_module.define_module_function("get", function!(rb_get, -1))?;
-    http_module.define_module_function("desktop", function!(rb_desktop, 0))?;
-    http_module.define_module_function("mobile", function!(rb_mobile, 0))?;
+    http_module.define_module_function("desktop", function!(rb_desktop, -1))?;
+    http_module.define_module_function("mobile", function!(rb_mobile, -1))?;
+    http_module.define_module_function("emulate", function!(rb_emulate, -1))?;
+    http_module.define_module_function("emulations", function!(rb_emulations, 0))?;
+    http_module.define_module_function("random_seed", function!(rb_random_seed, 0))?;
+    http_module.define_module_function("random_seed=", function!(rb_set_random_seed, 1))?;
     http_module.define_module_function("post", function!(rb_post, -1))?;
     http_module.define_module_function("put", function!(rb_put, -1))?;
     http_module.define_module_function("delete", function!(rb_delete, -1))?;
     http_module.define_module_function("head", function!(rb_head, -1))?;
     http_module.define_module_function("patch", function!(rb_patch, -1))?;
+    http_module.define_module_function("options", function!(rb_options, -1))?;
     http_module.define_module_function("request", function!(rb_request, -1))?;
     http_module.define_module_function("persistent", function!(rb_persistent, -1))?;
     http_module.define_module_function("headers", function!(rb_headers, 1))?;
@@ -1206,6 +1792,8 @@ fn init(ruby: &magnus::Ruby) -> Result<(), MagnusError> {
     http_module.define_module_function("auth", function!(rb_auth, 1))?;
     http_module.define_module_function("accept", function!(rb_accept, 1))?;
     http_module.define_module_function("encoding", function!(rb_encoding, 1))?;
+    http_module.define_module_function("limits", function!(rb_limits, 1))?;
+    http_module.define_module_function("compress", function!(rb_compress, 1))?;
 
     Ok(())
 }
@@ -1231,52 +1819,45 @@ mod tests {
         );
     }
 
-    // Tests for get_random_desktop_emulation() - returns valid variant
+    // Tests for get_random_desktop_emulation() - exact picks for a seed
     #[test]
     fn test_get_random_desktop_emulation_valid() {
-        let emulation = get_random_desktop_emulation();
-        // Check that we got one of the expected desktop variants
-        matches!(
-            emulation,
-            WreqEmulation::Chrome134
-                | WreqEmulation::Chrome128
-                | WreqEmulation::Chrome101
-                | WreqEmulation::Firefox135
-                | WreqEmulation::Safari17_0
-        );
+        let mut rng = EmulationRng::from_seed(42);
+        assert_eq!(get_random_desktop_emulation(&mut rng), Profile::Chrome101);
+        assert_eq!(get_random_desktop_emulation(&mut rng), Profile::Firefox135);
+        assert_eq!(get_random_desktop_emulation(&mut rng), Profile::Safari17_0);
     }
 
-    // Tests for get_random_mobile_emulation() - returns valid variant
+    // Tests for get_random_mobile_emulation() - exact picks for a seed
     #[test]
     fn test_get_random_mobile_emulation_valid() {
-        let emulation = get_random_mobile_emulation();
-        // Check that we got one of the expected mobile variants
-        matches!(
-            emulation,
-            WreqEmulation::SafariIos17_4_1
-                | WreqEmulation::SafariIos17_2
-                | WreqEmulation::SafariIos16_5
-                | WreqEmulation::FirefoxAndroid135
-        );
+        let mut rng = EmulationRng::from_seed(42);
+        assert_eq!(get_random_mobile_emulation(&mut rng), Profile::SafariIos16_5);
+        assert_eq!(get_random_mobile_emulation(&mut rng), Profile::FirefoxAndroid135);
+        assert_eq!(get_random_mobile_emulation(&mut rng), Profile::FirefoxAndroid135);
     }
 
-    // Tests for get_random_emulation() - returns valid variant (desktop or mobile)
+    // Tests for get_random_emulation() - exact picks (desktop or mobile) for a seed
     #[test]
     fn test_get_random_emulation_valid() {
-        let emulation = get_random_emulation();
-        // Should return either a desktop or mobile variant
-        matches!(
-            emulation,
-            WreqEmulation::Chrome134
-                | WreqEmulation::Chrome128
-                | WreqEmulation::Chrome101
-                | WreqEmulation::Firefox135
-                | WreqEmulation::Safari17_0
-                | WreqEmulation::SafariIos17_4_1
-                | WreqEmulation::SafariIos17_2
-                | WreqEmulation::SafariIos16_5
-                | WreqEmulation::FirefoxAndroid135
-        );
+        assert_eq!(get_random_emulation(&mut EmulationRng::from_seed(42)), Profile::Firefox135);
+        assert_eq!(get_random_emulation(&mut EmulationRng::from_seed(1)), Profile::Safari17_0);
+        assert_eq!(get_random_emulation(&mut EmulationRng::from_seed(0)), Profile::Chrome134);
+    }
+
+    #[test]
+    fn test_random_seed_replays_the_same_clients() {
+        set_random_seed(Some(7));
+        assert_eq!(random_seed(), Some(7));
+        let first: Vec<_> = (0..5).map(|_| get_random_emulation(&mut EmulationRng::next_client())).collect();
+
+        set_random_seed(Some(7));
+        fast_random(); // other randomness doesn't shift the sequence
+        let second: Vec<_> = (0..5).map(|_| get_random_emulation(&mut EmulationRng::next_client())).collect();
+        assert_eq!(first, second);
+
+        set_random_seed(None);
+        assert_eq!(random_seed(), None);
     }
 
     // Tests for normalize_header_name() - multiple test cases
@@ -1310,15 +1891,7 @@ mod tests {
         assert_eq!(result, "Content-Type");
     }
 
-    // Tests for HttpMethod enum - Copy/Clone traits
-    #[test]
-    fn test_http_method_copy() {
-        let method1 = HttpMethod::Get;
-        let method2 = method1; // Copy should work
-        assert!(matches!(method1, HttpMethod::Get));
-        assert!(matches!(method2, HttpMethod::Get));
-    }
-
+    // Tests for HttpMethod enum - Clone trait
     #[test]
     fn test_http_method_clone() {
         let method1 = HttpMethod::Post;
@@ -1327,6 +1900,34 @@ mod tests {
         assert!(matches!(method2, HttpMethod::Post));
     }
 
+    #[test]
+    fn test_http_method_as_str() {
+        assert_eq!(HttpMethod::Get.as_str(), "GET");
+        assert_eq!(HttpMethod::Options.as_str(), "OPTIONS");
+    }
+
+    #[test]
+    fn test_http_method_parse() {
+        assert_eq!(HttpMethod::parse("get").unwrap(), HttpMethod::Get);
+        assert_eq!(HttpMethod::parse("OPTIONS").unwrap(), HttpMethod::Options);
+        assert_eq!(HttpMethod::parse("propfind").unwrap().as_str(), "PROPFIND");
+        assert_eq!(
+            HttpMethod::parse("QUERY").unwrap(),
+            HttpMethod::Other(wreq::Method::from_bytes(b"QUERY").unwrap())
+        );
+        assert!(is_token("M-SEARCH"));
+        assert!(!is_token("BAD VERB"));
+        assert!(!is_token(""));
+    }
+
+    #[test]
+    fn test_extension_methods_compare_by_name() {
+        let purge = HttpMethod::parse("purge").unwrap();
+        assert_eq!(purge, HttpMethod::parse("PURGE").unwrap());
+        assert_eq!(purge.as_str(), "PURGE");
+        assert_ne!(purge, HttpMethod::parse("QUERY").unwrap());
+    }
+
     #[test]
     fn test_http_method_equality() {
         let get1 = HttpMethod::Get;
@@ -0,0 +1,139 @@
+use magnus::{exception, Error as MagnusError, RHash, Symbol, TryConvert, Value};
+use magnus::value::ReprValue;
+
+use crate::error::{ErrorKind, RequestError};
+
+/// Caps on how much of a response body is read. `None` means unlimited.
+///
+/// Set per client with `limits(...)` and per request in the options hash; request
+/// values override the client's one by one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BodyLimits {
+    /// Bytes received from the wire, before decompression
+    pub max_body_size: Option<u64>,
+    /// Bytes produced by decompression
+    pub max_decompressed_size: Option<u64>,
+    /// Decompressed bytes per received byte
+    pub max_compression_ratio: Option<f64>,
+}
+
+impl BodyLimits {
+    /// Reads `max_body_size:`, `max_decompressed_size:` and `max_compression_ratio:`.
+    /// Missing keys and `nil` leave the limit unset.
+    pub fn from_hash(hash: RHash) -> Result<Self, MagnusError> {
+        let max_body_size = fetch(hash, "max_body_size")?.map(u64::try_convert).transpose()?;
+        let max_decompressed_size = fetch(hash, "max_decompressed_size")?
+            .map(u64::try_convert)
+            .transpose()?;
+        let max_compression_ratio = fetch(hash, "max_compression_ratio")?
+            .map(f64::try_convert)
+            .transpose()?;
+
+        if max_compression_ratio.is_some_and(|ratio| ratio.is_nan() || ratio < 1.0) {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                "max_compression_ratio must be at least 1",
+            ));
+        }
+
+        Ok(Self {
+            max_body_size,
+            max_decompressed_size,
+            max_compression_ratio,
+        })
+    }
+
+    /// Limits set in `overrides` replace ours.
+    pub fn merge(self, overrides: BodyLimits) -> Self {
+        Self {
+            max_body_size: overrides.max_body_size.or(self.max_body_size),
+            max_decompressed_size: overrides.max_decompressed_size.or(self.max_decompressed_size),
+            max_compression_ratio: overrides.max_compression_ratio.or(self.max_compression_ratio),
+        }
+    }
+
+    /// Checks the bytes received so far, or the announced Content-Length.
+    pub fn check_body_size(&self, received: u64) -> Result<(), RequestError> {
+        match self.max_body_size {
+            Some(max) if received > max => Err(RequestError::new(
+                ErrorKind::BodyTooLarge,
+                format!("Response body exceeds max_body_size of {} bytes", max),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// How many decompressed bytes may have been produced once `received` bytes
+    /// have come off the wire.
+    pub fn decompressed_limit(&self, received: u64) -> Option<u64> {
+        let by_ratio = self
+            .max_compression_ratio
+            .map(|ratio| (received as f64 * ratio).ceil() as u64);
+        match (self.max_decompressed_size, by_ratio) {
+            (Some(max), Some(ratio)) => Some(max.min(ratio)),
+            (max, ratio) => max.or(ratio),
+        }
+    }
+
+    /// Error raised when a decoder hit `decompressed_limit`.
+    pub fn decompressed_error(&self, received: u64) -> RequestError {
+        let message = match self.max_decompressed_size {
+            Some(max) if self.decompressed_limit(received) == Some(max) => {
+                format!("Decompressed body exceeds max_decompressed_size of {} bytes", max)
+            }
+            _ => format!(
+                "Decompressed body exceeds max_compression_ratio of {}",
+                self.max_compression_ratio.unwrap_or_default()
+            ),
+        };
+        RequestError::new(ErrorKind::BodyTooLarge, message)
+    }
+}
+
+fn fetch(hash: RHash, key: &str) -> Result<Option<Value>, MagnusError> {
+    Ok(hash.get(Symbol::new(key)).filter(|value| !value.is_nil()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_unlimited() {
+        let limits = BodyLimits::default();
+        assert!(limits.check_body_size(u64::MAX).is_ok());
+        assert_eq!(limits.decompressed_limit(1024), None);
+    }
+
+    #[test]
+    fn test_check_body_size() {
+        let limits = BodyLimits { max_body_size: Some(100), ..Default::default() };
+        assert!(limits.check_body_size(100).is_ok());
+        assert!(limits.check_body_size(101).is_err());
+    }
+
+    #[test]
+    fn test_decompressed_limit_takes_the_smaller_bound() {
+        let limits = BodyLimits {
+            max_decompressed_size: Some(10_000),
+            max_compression_ratio: Some(50.0),
+            ..Default::default()
+        };
+        assert_eq!(limits.decompressed_limit(10), Some(500));
+        assert_eq!(limits.decompressed_limit(1_000), Some(10_000));
+    }
+
+    #[test]
+    fn test_merge_prefers_overrides() {
+        let client = BodyLimits {
+            max_body_size: Some(1_000),
+            max_decompressed_size: Some(5_000),
+            ..Default::default()
+        };
+        let request = BodyLimits { max_body_size: Some(10), ..Default::default() };
+        let merged = client.merge(request);
+        assert_eq!(merged.max_body_size, Some(10));
+        assert_eq!(merged.max_decompressed_size, Some(5_000));
+        assert_eq!(merged.max_compression_ratio, None);
+    }
+}
@@ -0,0 +1,310 @@
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+use magnus::value::ReprValue;
+use magnus::{exception, Error as MagnusError, RHash, RString, Value};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::body::io_error;
+use crate::params::{self, ArrayStyle};
+use crate::string_bytes;
+
+/// Largest read a file part is streamed in.
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A multipart/form-data body (RFC 7578). File parts are opened up front, so a
+/// missing file fails before anything is sent, and read from disk only as the
+/// body goes out.
+pub struct Form {
+    boundary: String,
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    Bytes(Bytes),
+    File { file: File, len: u64 },
+}
+
+impl Form {
+    pub fn new() -> Self {
+        Self::with_boundary(format!(
+            "----WreqFormBoundary{:016x}{:016x}",
+            crate::fast_random(),
+            crate::fast_random()
+        ))
+    }
+
+    fn with_boundary(boundary: String) -> Self {
+        Self {
+            boundary,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Builds the form from a `form:` hash, or returns `None` when no value is a
+    /// file part and a urlencoded form will do. File parts are anything that
+    /// responds to `filename` and `content_type`, like `HTTP::FormData::File`.
+    /// Part names are flattened the same way as the urlencoded form, nested
+    /// hashes and `array_style:` included.
+    pub fn from_hash(hash: RHash, style: ArrayStyle) -> Result<Option<Self>, MagnusError> {
+        let leaves = params::flatten_values(hash, style)?;
+        let mut has_files = false;
+        for (_, value) in &leaves {
+            has_files = has_files || is_file_part(*value)?;
+        }
+        if !has_files {
+            return Ok(None);
+        }
+
+        let mut form = Self::new();
+        for (name, value) in leaves {
+            form.add_value(&name, value)?;
+        }
+        Ok(Some(form))
+    }
+
+    fn add_value(&mut self, name: &str, value: Value) -> Result<(), MagnusError> {
+        if !is_file_part(value)? {
+            // Multipart has no key-only field, so nil sends an empty part
+            let text = value.to_r_string()?;
+            self.bytes(name, None, None, string_bytes(text));
+            return Ok(());
+        }
+
+        let filename: Option<String> = value.funcall("filename", ())?;
+        let content_type: Option<String> = value.funcall("content_type", ())?;
+        // Written into the part headers as is: a line break would start a new header
+        if let Some(content_type) = content_type.as_deref().filter(|ct| ct.contains(['\r', '\n'])) {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                format!("invalid content type for {:?}: {:?}", name, content_type),
+            ));
+        }
+        let path: Option<String> = if value.respond_to("path", false)? {
+            value.funcall("path", ())?
+        } else {
+            None
+        };
+
+        match path.filter(|path| Path::new(path).is_file()) {
+            Some(path) => self
+                .file(name, Path::new(&path), filename.as_deref(), content_type.as_deref())
+                .map_err(|e| io_error(Path::new(&path), e)),
+            None => {
+                // An IO that isn't a file on disk: take its contents as they are
+                let data: RString = value.funcall("to_s", ())?;
+                self.bytes(name, filename.as_deref(), content_type.as_deref(), string_bytes(data));
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a part whose contents are already in memory.
+    pub fn bytes(&mut self, name: &str, filename: Option<&str>, content_type: Option<&str>, data: Bytes) {
+        self.part_headers(name, filename, content_type);
+        self.segments.push(Segment::Bytes(data));
+        self.segments.push(Segment::Bytes(Bytes::from_static(b"\r\n")));
+    }
+
+    /// Adds a part streamed from the file at `path`.
+    pub fn file(
+        &mut self,
+        name: &str,
+        path: &Path,
+        filename: Option<&str>,
+        content_type: Option<&str>,
+    ) -> io::Result<()> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        self.part_headers(name, filename, content_type);
+        self.segments.push(Segment::File { file, len });
+        self.segments.push(Segment::Bytes(Bytes::from_static(b"\r\n")));
+        Ok(())
+    }
+
+    fn part_headers(&mut self, name: &str, filename: Option<&str>, content_type: Option<&str>) {
+        let mut headers = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape_quoted(name)
+        );
+        if let Some(filename) = filename {
+            headers.push_str(&format!("; filename=\"{}\"", escape_quoted(filename)));
+        }
+        headers.push_str("\r\n");
+        if let Some(content_type) = content_type {
+            headers.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        headers.push_str("\r\n");
+        self.segments.push(Segment::Bytes(Bytes::from(headers)));
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    pub fn into_body(mut self) -> FormBody {
+        let closing = format!("--{}--\r\n", self.boundary);
+        self.segments.push(Segment::Bytes(Bytes::from(closing)));
+
+        let remaining = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Bytes(bytes) => bytes.len() as u64,
+                Segment::File { len, .. } => *len,
+            })
+            .sum();
+        let segments = self
+            .segments
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Bytes(bytes) => BodySegment::Bytes(bytes),
+                Segment::File { file, len } => BodySegment::File {
+                    file: tokio::fs::File::from_std(file),
+                    len,
+                },
+            })
+            .collect();
+
+        FormBody { segments, remaining }
+    }
+}
+
+/// The encoded form as it is sent. Its exact length is known up front, so it
+/// goes out with a Content-Length rather than chunked.
+pub struct FormBody {
+    segments: VecDeque<BodySegment>,
+    remaining: u64,
+}
+
+enum BodySegment {
+    Bytes(Bytes),
+    // Bytes still to be read from the file
+    File { file: tokio::fs::File, len: u64 },
+}
+
+impl Body for FormBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        let this = &mut *self;
+        loop {
+            let Some(segment) = this.segments.front_mut() else {
+                return Poll::Ready(None);
+            };
+            let chunk = match segment {
+                BodySegment::Bytes(bytes) => std::mem::take(bytes),
+                BodySegment::File { len: 0, .. } => Bytes::new(),
+                BodySegment::File { file, len } => {
+                    let mut buf = vec![0; FILE_CHUNK_SIZE.min(*len as usize)];
+                    let mut read_buf = ReadBuf::new(&mut buf);
+                    ready!(Pin::new(file).poll_read(cx, &mut read_buf))?;
+                    let read = read_buf.filled().len();
+                    if read == 0 {
+                        return Poll::Ready(Some(Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "file was truncated while being uploaded",
+                        ))));
+                    }
+                    // Stay on this file until it has been read to its length
+                    *len -= read as u64;
+                    this.remaining -= read as u64;
+                    buf.truncate(read);
+                    return Poll::Ready(Some(Ok(Frame::data(Bytes::from(buf)))));
+                }
+            };
+
+            this.segments.pop_front();
+            if !chunk.is_empty() {
+                this.remaining -= chunk.len() as u64;
+                return Poll::Ready(Some(Ok(Frame::data(chunk))));
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+fn is_file_part(value: Value) -> Result<bool, MagnusError> {
+    Ok(value.respond_to("filename", false)? && value.respond_to("content_type", false)?)
+}
+
+/// Quoted-string values of Content-Disposition, escaped the way browsers do.
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use std::io::Write;
+
+    fn collect(form: Form) -> (Vec<u8>, Option<u64>) {
+        let body = form.into_body();
+        let len = body.size_hint().exact();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let bytes = runtime.block_on(body.collect()).unwrap().to_bytes();
+        (bytes.to_vec(), len)
+    }
+
+    #[test]
+    fn test_text_and_file_parts() {
+        let path = std::env::temp_dir().join(format!("wreq_multipart_{}.txt", std::process::id()));
+        File::create(&path).unwrap().write_all(b"file contents").unwrap();
+
+        let mut form = Form::with_boundary("XYZ".to_string());
+        form.bytes("name", None, None, Bytes::from_static(b"Alice"));
+        form.file("upload", &path, Some("a.txt"), Some("text/plain")).unwrap();
+        let (body, len) = collect(form);
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = "--XYZ\r\n\
+            Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+            Alice\r\n\
+            --XYZ\r\n\
+            Content-Disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            file contents\r\n\
+            --XYZ--\r\n";
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+        assert_eq!(len, Some(expected.len() as u64));
+    }
+
+    #[test]
+    fn test_quotes_and_newlines_are_escaped() {
+        assert_eq!(escape_quoted("a\"b\r\nc.txt"), "a%22b%0D%0Ac.txt");
+    }
+
+    #[test]
+    fn test_missing_file_fails_up_front() {
+        let mut form = Form::with_boundary("XYZ".to_string());
+        let err = form.file("upload", Path::new("/nonexistent/wreq"), None, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_content_type_carries_boundary() {
+        let form = Form::with_boundary("XYZ".to_string());
+        assert_eq!(form.content_type(), "multipart/form-data; boundary=XYZ");
+    }
+}
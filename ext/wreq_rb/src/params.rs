@@ -0,0 +1,208 @@
+use magnus::r_hash::ForEach;
+use magnus::value::ReprValue;
+use magnus::{exception, Error as MagnusError, RArray, RHash, RString, Symbol, TryConvert, Value};
+use url::Url;
+
+/// Deepest nesting of Hashes and Arrays accepted, which also stops
+/// self-referencing structures.
+const MAX_DEPTH: usize = 32;
+
+/// How Array values of `params:` and `form:` are written out, chosen per request
+/// with `array_style:`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ArrayStyle {
+    /// `a[]=1&a[]=2`, what Rack and Rails read
+    #[default]
+    Brackets,
+    /// `a=1&a=2`
+    Repeat,
+    /// `a[0]=1&a[1]=2`
+    Indexed,
+}
+
+impl ArrayStyle {
+    /// Reads `array_style:` (`:brackets`, `:repeat` or `:indexed`) from the
+    /// options hash.
+    pub fn from_hash(hash: RHash) -> Result<Self, MagnusError> {
+        let Some(value) = hash.get(Symbol::new("array_style")) else {
+            return Ok(Self::default());
+        };
+        match &*Symbol::try_convert(value)?.name()? {
+            "brackets" => Ok(ArrayStyle::Brackets),
+            "repeat" => Ok(ArrayStyle::Repeat),
+            "indexed" => Ok(ArrayStyle::Indexed),
+            name => Err(MagnusError::new(
+                exception::arg_error(),
+                format!("unknown array_style: :{} (expected :brackets, :repeat or :indexed)", name),
+            )),
+        }
+    }
+
+    fn key(self, prefix: &str, index: usize) -> String {
+        match self {
+            ArrayStyle::Brackets => format!("{}[]", prefix),
+            ArrayStyle::Repeat => prefix.to_string(),
+            ArrayStyle::Indexed => format!("{}[{}]", prefix, index),
+        }
+    }
+}
+
+/// One flattened parameter. `nil` values become a key without a value.
+pub type Pair = (String, Option<String>);
+
+/// Flattens a `params:`/`form:` hash in its own order. Nested hashes become
+/// `a[b]=...`; Integers, Floats, booleans and anything else are written with
+/// `to_s`.
+pub fn flatten(hash: RHash, style: ArrayStyle) -> Result<Vec<Pair>, MagnusError> {
+    flatten_values(hash, style)?
+        .into_iter()
+        .map(|(key, value)| Ok((key, scalar(value)?)))
+        .collect()
+}
+
+/// Like `flatten`, but leaves the values as they are, for multipart forms
+/// whose file parts can't be written with `to_s`.
+pub fn flatten_values(hash: RHash, style: ArrayStyle) -> Result<Vec<(String, Value)>, MagnusError> {
+    let mut leaves = Vec::new();
+    flatten_hash(None, hash, style, &mut leaves, 0)?;
+    Ok(leaves)
+}
+
+fn flatten_hash(
+    prefix: Option<&str>,
+    hash: RHash,
+    style: ArrayStyle,
+    leaves: &mut Vec<(String, Value)>,
+    depth: usize,
+) -> Result<(), MagnusError> {
+    hash.foreach(|key: Value, value: Value| {
+        let key = key_string(key)?;
+        let key = match prefix {
+            Some(prefix) => format!("{}[{}]", prefix, key),
+            None => key,
+        };
+        flatten_value(&key, value, style, leaves, depth)?;
+        Ok(ForEach::Continue)
+    })
+}
+
+fn flatten_value(
+    key: &str,
+    value: Value,
+    style: ArrayStyle,
+    leaves: &mut Vec<(String, Value)>,
+    depth: usize,
+) -> Result<(), MagnusError> {
+    if let Some(hash) = RHash::from_value(value) {
+        flatten_hash(Some(key), hash, style, leaves, nested(depth)?)
+    } else if let Some(array) = RArray::from_value(value) {
+        let depth = nested(depth)?;
+        for (index, element) in array.into_iter().enumerate() {
+            flatten_value(&style.key(key, index), element, style, leaves, depth)?;
+        }
+        Ok(())
+    } else {
+        leaves.push((key.to_string(), value));
+        Ok(())
+    }
+}
+
+fn nested(depth: usize) -> Result<usize, MagnusError> {
+    if depth >= MAX_DEPTH {
+        return Err(MagnusError::new(exception::arg_error(), "params are nested too deeply"));
+    }
+    Ok(depth + 1)
+}
+
+/// Hash keys are Symbols or Strings.
+pub fn key_string(key: Value) -> Result<String, MagnusError> {
+    match Symbol::from_value(key) {
+        Some(sym) => Ok(sym.name()?.to_string()),
+        None => String::try_convert(key),
+    }
+}
+
+fn scalar(value: Value) -> Result<Option<String>, MagnusError> {
+    if value.is_nil() {
+        return Ok(None);
+    }
+    if let Some(string) = RString::from_value(value) {
+        return string.to_string().map(Some);
+    }
+    if let Some(sym) = Symbol::from_value(value) {
+        return Ok(Some(sym.name()?.to_string()));
+    }
+    value.to_r_string()?.to_string().map(Some)
+}
+
+/// `application/x-www-form-urlencoded` body.
+pub fn form_body(pairs: &[Pair]) -> String {
+    pairs
+        .iter()
+        .map(|(key, value)| match value {
+            Some(value) => format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)),
+            None => urlencoding::encode(key).into_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Appends to the URL's query. A query already in the URL is kept as it is and
+/// comes first, so the result doesn't depend on anything but the two inputs.
+pub fn append_query(url: &mut Url, pairs: &[Pair]) {
+    if pairs.is_empty() {
+        return;
+    }
+    let mut query = url.query_pairs_mut();
+    for (key, value) in pairs {
+        match value {
+            Some(value) => query.append_pair(key, value),
+            None => query.append_key_only(key),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, Option<&str>)]) -> Vec<Pair> {
+        items
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn test_array_keys() {
+        assert_eq!(ArrayStyle::Brackets.key("a", 1), "a[]");
+        assert_eq!(ArrayStyle::Repeat.key("a", 1), "a");
+        assert_eq!(ArrayStyle::Indexed.key("a", 1), "a[1]");
+        assert_eq!(ArrayStyle::Indexed.key("a[b]", 0), "a[b][0]");
+    }
+
+    #[test]
+    fn test_form_body() {
+        let body = form_body(&pairs(&[
+            ("page", Some("2")),
+            ("q", Some("a b&c")),
+            ("a[]", Some("1")),
+            ("flag", None),
+        ]));
+        assert_eq!(body, "page=2&q=a%20b%26c&a%5B%5D=1&flag");
+    }
+
+    #[test]
+    fn test_existing_query_comes_first() {
+        let mut url = Url::parse("https://example.com/search?q=rust#top").unwrap();
+        append_query(&mut url, &pairs(&[("page", Some("2")), ("q", Some("ruby"))]));
+        assert_eq!(url.as_str(), "https://example.com/search?q=rust&page=2&q=ruby#top");
+    }
+
+    #[test]
+    fn test_no_params_leaves_url_alone() {
+        let mut url = Url::parse("https://example.com/").unwrap();
+        append_query(&mut url, &[]);
+        assert_eq!(url.as_str(), "https://example.com/");
+    }
+}
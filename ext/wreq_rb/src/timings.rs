@@ -0,0 +1,378 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+use wreq::dns::{GaiResolver, Name, Resolve, Resolving};
+use wreq::tls::session::{Key, LruTlsSessionCache, TlsSession, TlsSessionCache};
+
+/// Session cache capacity per host, same as wreq's default cache.
+const TLS_SESSIONS_PER_HOST: usize = 8;
+
+tokio::task_local! {
+    // Trace of the request currently being sent on this task
+    static TRACE: SharedTrace;
+}
+
+pub type SharedTrace = Arc<Mutex<Trace>>;
+
+/// What the hooks below saw while one request was being sent.
+#[derive(Default)]
+pub struct Trace {
+    pub phases: ConnectPhases,
+    /// When each redirect response arrived, in order
+    pub redirects: Vec<Instant>,
+}
+
+/// Time spent setting up connections for one request, summed over every
+/// connection it opened (a redirect to another host opens a new one). All zero
+/// when the request went out on a pooled connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConnectPhases {
+    pub dns: Duration,
+    /// TCP connect, plus the TLS handshake when it couldn't be told apart
+    pub connect: Duration,
+    pub tls: Duration,
+    /// Set when a connection was opened without its handshake being timed, so
+    /// `tls` doesn't cover every connection
+    pub tls_unmeasured: bool,
+    // DNS time of the connection being established
+    pending_dns: Duration,
+    // When the TLS handshake of that connection started
+    tls_started: Option<Instant>,
+}
+
+impl ConnectPhases {
+    fn record_dns(&mut self, elapsed: Duration) {
+        self.pending_dns += elapsed;
+    }
+
+    fn record_tls_start(&mut self, at: Instant) {
+        self.tls_started = Some(at);
+    }
+
+    /// Splits one connection's setup time into DNS, TCP and TLS.
+    fn record_connection(&mut self, started: Instant, finished: Instant) {
+        let total = finished.saturating_duration_since(started);
+        let dns = std::mem::take(&mut self.pending_dns);
+        let tls = match self.tls_started.take() {
+            Some(at) => finished.saturating_duration_since(at),
+            None => {
+                self.tls_unmeasured = true;
+                Duration::ZERO
+            }
+        };
+
+        self.dns += dns;
+        self.tls += tls;
+        self.connect += total.saturating_sub(dns).saturating_sub(tls);
+    }
+}
+
+/// Sends a request with its connection phases and redirects recorded into `trace`.
+pub async fn track<F: Future>(trace: SharedTrace, future: F) -> F::Output {
+    TRACE.scope(trace, future).await
+}
+
+pub fn shared_trace() -> SharedTrace {
+    Arc::default()
+}
+
+/// Called by the redirect policy as each redirect response comes in.
+pub fn record_redirect() {
+    if let Some(trace) = current() {
+        with_trace(&trace, |t| t.redirects.push(Instant::now()));
+    }
+}
+
+/// When each redirect response of the traced request arrived.
+pub fn redirect_times(trace: &SharedTrace) -> Vec<Instant> {
+    with_trace(trace, |t| t.redirects.clone())
+}
+
+fn current() -> Option<SharedTrace> {
+    TRACE.try_with(Arc::clone).ok()
+}
+
+fn with_trace<T>(trace: &SharedTrace, f: impl FnOnce(&mut Trace) -> T) -> T {
+    f(&mut trace.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+/// Installs the hooks `ConnectPhases` is collected from.
+///
+/// - DNS: a resolver wrapping wreq's default one.
+/// - Connect: a connector layer around DNS + TCP + TLS.
+/// - TLS: the session cache is consulted right before the handshake starts, after
+///   the TCP connection is up. wreq only does so when the profile enables session
+///   resumption (recent Chrome, Firefox and Opera, not Safari or OkHttp); without it the
+///   handshake is counted in `connect` and `tls` is reported as unmeasured.
+pub fn instrument(builder: wreq::ClientBuilder) -> wreq::ClientBuilder {
+    builder
+        .dns_resolver(TimedResolver(GaiResolver::new()))
+        .tls_session_cache(TimedSessionCache(LruTlsSessionCache::new(TLS_SESSIONS_PER_HOST)))
+        .connector_layer(ConnectTimingLayer)
+}
+
+struct TimedResolver(GaiResolver);
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolving = self.0.resolve(name);
+        let Some(trace) = current() else {
+            return resolving;
+        };
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = resolving.await;
+            with_trace(&trace, |t| t.phases.record_dns(started.elapsed()));
+            result
+        })
+    }
+}
+
+struct TimedSessionCache(LruTlsSessionCache);
+
+impl TlsSessionCache for TimedSessionCache {
+    fn put(&self, key: Key, session: TlsSession) {
+        self.0.put(key, session)
+    }
+
+    fn pop(&self, key: &Key) -> Option<TlsSession> {
+        if let Some(trace) = current() {
+            with_trace(&trace, |t| t.phases.record_tls_start(Instant::now()));
+        }
+        self.0.pop(key)
+    }
+}
+
+#[derive(Clone)]
+struct ConnectTimingLayer;
+
+impl<S> Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming(inner)
+    }
+}
+
+#[derive(Clone)]
+struct ConnectTiming<S>(S);
+
+impl<S, R> Service<R> for ConnectTiming<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.0.call(request);
+        let Some(trace) = current() else {
+            return Box::pin(connecting);
+        };
+        Box::pin(async move {
+            let started = Instant::now();
+            // The pool may finish this connection on another task: keep the
+            // trace in scope for the resolver and session cache hooks.
+            let result = TRACE.scope(Arc::clone(&trace), connecting).await;
+            with_trace(&trace, |t| t.phases.record_connection(started, Instant::now()));
+            result
+        })
+    }
+}
+
+/// Timestamps of one request, from `send()` to the end of the body.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestTimings {
+    pub started_at: Instant,
+    pub headers_at: Instant,
+    pub phases: ConnectPhases,
+    /// `None` until the body has been read to the end
+    pub finished_at: Option<Instant>,
+}
+
+impl RequestTimings {
+    pub fn new(started_at: Instant, trace: &SharedTrace) -> Self {
+        let phases = with_trace(trace, |t| t.phases);
+        Self {
+            started_at,
+            headers_at: Instant::now(),
+            phases,
+            finished_at: None,
+        }
+    }
+
+    /// Waiting for the response headers once the connection was ready.
+    fn ttfb(&self) -> Duration {
+        let setup = self.phases.dns + self.phases.connect + self.phases.tls;
+        self.headers_at
+            .saturating_duration_since(self.started_at)
+            .saturating_sub(setup)
+    }
+}
+
+/// `Wreq::HTTP::Timings`, returned by `Response#timings`. Every phase is in
+/// seconds and they add up to `total`.
+#[magnus::wrap(class = "Wreq::HTTP::Timings")]
+pub struct RbTimings {
+    timings: RequestTimings,
+}
+
+impl RbTimings {
+    pub fn new(timings: RequestTimings) -> Self {
+        Self { timings }
+    }
+
+    pub fn dns(&self) -> f64 {
+        self.timings.phases.dns.as_secs_f64()
+    }
+
+    pub fn connect(&self) -> f64 {
+        self.timings.phases.connect.as_secs_f64()
+    }
+
+    /// `nil` when a new connection's handshake couldn't be told apart from the
+    /// TCP connect (plain HTTP, or a profile without session resumption).
+    pub fn tls(&self) -> Option<f64> {
+        let phases = &self.timings.phases;
+        (!phases.tls_unmeasured).then(|| phases.tls.as_secs_f64())
+    }
+
+    pub fn ttfb(&self) -> f64 {
+        self.timings.ttfb().as_secs_f64()
+    }
+
+    /// `nil` while a streamed body hasn't been read to the end.
+    pub fn download(&self) -> Option<f64> {
+        self.timings
+            .finished_at
+            .map(|at| at.saturating_duration_since(self.timings.headers_at).as_secs_f64())
+    }
+
+    /// `nil` while a streamed body hasn't been read to the end.
+    pub fn total(&self) -> Option<f64> {
+        self.timings
+            .finished_at
+            .map(|at| at.saturating_duration_since(self.timings.started_at).as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_connection_split_into_phases() {
+        let started = Instant::now();
+        let mut phases = ConnectPhases::default();
+        phases.record_dns(ms(20));
+        phases.record_tls_start(started + ms(50));
+        phases.record_connection(started, started + ms(120));
+
+        assert_eq!(phases.dns, ms(20));
+        assert_eq!(phases.connect, ms(30));
+        assert_eq!(phases.tls, ms(70));
+        assert!(!phases.tls_unmeasured);
+    }
+
+    #[test]
+    fn test_without_tls_marker_handshake_counts_as_connect() {
+        let started = Instant::now();
+        let mut phases = ConnectPhases::default();
+        phases.record_dns(ms(10));
+        phases.record_connection(started, started + ms(100));
+
+        assert_eq!(phases.connect, ms(90));
+        assert_eq!(phases.tls, Duration::ZERO);
+        assert!(phases.tls_unmeasured);
+    }
+
+    #[test]
+    fn test_tls_is_nil_when_a_handshake_went_unmeasured() {
+        let started = Instant::now();
+        let timings = |phases| RequestTimings {
+            started_at: started,
+            headers_at: started + ms(200),
+            phases,
+            finished_at: None,
+        };
+
+        // Safari/OkHttp profiles never consult the session cache
+        let mut phases = ConnectPhases::default();
+        phases.record_connection(started, started + ms(100));
+        assert_eq!(RbTimings::new(timings(phases)).tls(), None);
+
+        // One measured connection doesn't make up for an unmeasured one
+        phases.record_tls_start(started + ms(150));
+        phases.record_connection(started + ms(120), started + ms(180));
+        assert_eq!(RbTimings::new(timings(phases)).tls(), None);
+
+        // A pooled connection opens nothing and has nothing to measure
+        assert_eq!(RbTimings::new(timings(ConnectPhases::default())).tls(), Some(0.0));
+    }
+
+    #[test]
+    fn test_connections_accumulate() {
+        let started = Instant::now();
+        let mut phases = ConnectPhases::default();
+        phases.record_dns(ms(10));
+        phases.record_connection(started, started + ms(40));
+        phases.record_dns(ms(5));
+        phases.record_tls_start(started + ms(60));
+        phases.record_connection(started + ms(50), started + ms(80));
+
+        assert_eq!(phases.dns, ms(15));
+        assert_eq!(phases.connect, ms(35));
+        assert_eq!(phases.tls, ms(20));
+        assert!(phases.tls_unmeasured);
+    }
+
+    #[test]
+    fn test_ttfb_excludes_connection_setup() {
+        let started_at = Instant::now();
+        let timings = RequestTimings {
+            started_at,
+            headers_at: started_at + ms(300),
+            phases: ConnectPhases {
+                dns: ms(20),
+                connect: ms(30),
+                tls: ms(50),
+                ..Default::default()
+            },
+            finished_at: Some(started_at + ms(400)),
+        };
+        assert_eq!(timings.ttfb(), ms(200));
+
+        let rb = RbTimings::new(timings);
+        assert_eq!(rb.download(), Some(0.1));
+        assert_eq!(rb.total(), Some(0.4));
+    }
+
+    #[test]
+    fn test_track_scopes_trace() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let trace = shared_trace();
+        runtime.block_on(track(Arc::clone(&trace), async {
+            with_trace(&current().unwrap(), |t| t.phases.record_dns(ms(7)));
+            record_redirect();
+        }));
+        assert_eq!(trace.lock().unwrap().phases.pending_dns, ms(7));
+        assert_eq!(trace.lock().unwrap().redirects.len(), 1);
+
+        // Outside a tracked request the hooks do nothing
+        record_redirect();
+        assert!(current().is_none());
+    }
+}
@@ -0,0 +1,244 @@
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+use magnus::value::{BoxValue, ReprValue};
+use magnus::{exception, Error as MagnusError, RHash, RString, Symbol, Value};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+
+use crate::{gvl, string_bytes};
+
+/// Bytes asked of the source per `readpartial`/`read`.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A request body read from a Ruby object while the request is being sent:
+/// anything responding to `readpartial` or `read` (IO, File, StringIO) or to
+/// `each` (Enumerator, Array of strings), like http.rb accepts.
+///
+/// Ruby objects can only be touched with the GVL held on the calling thread, so
+/// the body wreq sends is fed through a one-chunk channel: `run` waits with the
+/// GVL released until the body wants more, then takes the GVL just long enough
+/// to read the next chunk.
+pub struct Upload {
+    source: Source,
+    // Exact length, when the source can tell
+    len: Option<u64>,
+}
+
+enum Source {
+    ReadPartial(BoxValue<Value>),
+    Read(BoxValue<Value>),
+    // External enumerator over `each`
+    Each(BoxValue<Value>),
+}
+
+impl Upload {
+    /// Returns `None` if `value` can't be read from. Raises ArgumentError for a
+    /// Hash or a Pathname, whose `each`/`read` would send something else than
+    /// the caller meant.
+    pub fn from_value(value: Value) -> Result<Option<Self>, MagnusError> {
+        let source = if value.respond_to("readpartial", false)? {
+            Source::ReadPartial(BoxValue::new(value))
+        } else if RHash::from_value(value).is_some() {
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                "body: can't be a Hash, use form: or json: instead",
+            ));
+        } else if value.respond_to("to_path", false)? {
+            // A File would have readpartial: this is a path, like Pathname
+            return Err(MagnusError::new(
+                exception::arg_error(),
+                format!("body: can't be a path ({}), pass File.open(path) instead", value.inspect()),
+            ));
+        } else if value.respond_to("read", false)? {
+            Source::Read(BoxValue::new(value))
+        } else if value.respond_to("each", false)? {
+            let enumerator: Value = value.funcall("to_enum", (Symbol::new("each"),))?;
+            Source::Each(BoxValue::new(enumerator))
+        } else {
+            return Ok(None);
+        };
+
+        // File and StringIO know how much is left; pipes and sockets don't
+        let len = match source {
+            Source::Each(_) => None,
+            _ if value.respond_to("size", false)? && value.respond_to("pos", false)? => {
+                let size: u64 = value.funcall("size", ())?;
+                let pos: u64 = value.funcall("pos", ())?;
+                Some(size.saturating_sub(pos))
+            }
+            _ => None,
+        };
+
+        Ok(Some(Self { source, len }))
+    }
+
+    /// Splits into the body handed to wreq and the pump that feeds it.
+    pub fn into_body(self) -> (UploadPump, UploadBody) {
+        let (sender, receiver) = mpsc::channel(1);
+        let body = UploadBody {
+            receiver,
+            remaining: self.len,
+        };
+        let pump = UploadPump {
+            source: self.source,
+            sender: Some(sender),
+        };
+        (pump, body)
+    }
+}
+
+/// Feeds an `UploadBody` from the Ruby thread.
+pub struct UploadPump {
+    source: Source,
+    // Dropped at the end of the source, which ends the body
+    sender: Option<mpsc::Sender<Bytes>>,
+}
+
+enum Step<T> {
+    Done(T),
+    // Room for one chunk, reserved until it is sent or dropped
+    Read(mpsc::OwnedPermit<Bytes>),
+    // The body was dropped: sent in full or the request gave up on it
+    Closed,
+}
+
+impl UploadPump {
+    /// Drives `future` (the request) to completion like
+    /// `gvl::block_on_interruptible`, reading the source whenever the body has
+    /// room for another chunk. Errors raised by the source are returned as is and
+    /// cancel the request.
+    pub fn run<F>(mut self, runtime: &Runtime, future: F) -> Result<Option<F::Output>, MagnusError>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let mut future = std::pin::pin!(future);
+        loop {
+            let sender = &self.sender;
+            let step = gvl::block_on_interruptible(runtime, async {
+                let Some(sender) = sender else {
+                    return Step::Done(future.as_mut().await);
+                };
+                tokio::select! {
+                    biased;
+                    output = future.as_mut() => Step::Done(output),
+                    permit = sender.clone().reserve_owned() => match permit {
+                        Ok(permit) => Step::Read(permit),
+                        Err(_) => Step::Closed,
+                    },
+                }
+            });
+
+            match step {
+                None => return Ok(None),
+                Some(Step::Done(output)) => return Ok(Some(output)),
+                Some(Step::Closed) => self.sender = None,
+                Some(Step::Read(permit)) => match self.source.next_chunk()? {
+                    Some(chunk) => {
+                        permit.send(chunk);
+                    }
+                    // The permit holds a sender too: both go here, ending the body
+                    None => self.sender = None,
+                },
+            }
+        }
+    }
+}
+
+impl Source {
+    /// Next non-empty chunk, or `None` at the end. Called with the GVL held.
+    fn next_chunk(&self) -> Result<Option<Bytes>, MagnusError> {
+        loop {
+            let chunk: Option<RString> = match self {
+                Source::ReadPartial(io) => match io.funcall("readpartial", (CHUNK_SIZE,)) {
+                    Ok(chunk) => Some(chunk),
+                    Err(e) if e.is_kind_of(exception::eof_error()) => None,
+                    Err(e) => return Err(e),
+                },
+                Source::Read(io) => io.funcall("read", (CHUNK_SIZE,))?,
+                Source::Each(enumerator) => match enumerator.funcall::<_, _, Value>("next", ()) {
+                    Ok(chunk) => Some(chunk.to_r_string()?),
+                    Err(e) if e.is_kind_of(exception::stop_iteration()) => None,
+                    Err(e) => return Err(e),
+                },
+            };
+            let Some(chunk) = chunk else {
+                return Ok(None);
+            };
+            let chunk = string_bytes(chunk);
+            if !chunk.is_empty() {
+                return Ok(Some(chunk));
+            }
+        }
+    }
+}
+
+/// The body wreq sends for an `Upload`. Goes out with a Content-Length when the
+/// source's size is known, chunked otherwise.
+pub struct UploadBody {
+    receiver: mpsc::Receiver<Bytes>,
+    remaining: Option<u64>,
+}
+
+impl Body for UploadBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        let chunk = ready!(self.receiver.poll_recv(cx));
+        if let (Some(chunk), Some(remaining)) = (&chunk, &mut self.remaining) {
+            *remaining = remaining.saturating_sub(chunk.len() as u64);
+        }
+        Poll::Ready(chunk.map(|chunk| Ok(Frame::data(chunk))))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.remaining {
+            Some(remaining) => SizeHint::with_exact(remaining),
+            None => SizeHint::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[test]
+    fn test_body_yields_sent_chunks_then_ends() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (sender, receiver) = mpsc::channel(2);
+        let body = UploadBody {
+            receiver,
+            remaining: Some(10),
+        };
+        assert_eq!(body.size_hint().exact(), Some(10));
+
+        runtime.block_on(async {
+            sender.send(Bytes::from_static(b"hello")).await.unwrap();
+            sender.send(Bytes::from_static(b"world")).await.unwrap();
+        });
+        drop(sender);
+
+        let collected = runtime.block_on(body.collect()).unwrap().to_bytes();
+        assert_eq!(&collected[..], b"helloworld");
+    }
+
+    #[test]
+    fn test_unknown_length_is_chunked() {
+        let (_sender, receiver) = mpsc::channel(1);
+        let body = UploadBody {
+            receiver,
+            remaining: None,
+        };
+        assert_eq!(body.size_hint().exact(), None);
+    }
+}
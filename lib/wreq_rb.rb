@@ -108,42 +108,184 @@ module Wreq
       end
     end
 
+    # Response headers. Lookups are case-insensitive and repeated headers keep
+    # every value; the native side provides [], get, to_a, to_h, keys, include?, size.
+    # #each yields names in the order they first appeared, each name's values in
+    # the order they were received.
+    class Headers
+      include Enumerable
+
+      def each
+        return to_enum(:each) { to_a.size } unless block_given?
+
+        to_a.each { |name, value| yield name, value }
+        self
+      end
+
+      alias key? include?
+      alias length size
+
+      def is_a?(klass)
+        klass == Hash || super
+      end
+      alias kind_of? is_a?
+
+      def inspect
+        "#<Wreq::HTTP::Headers #{to_h.inspect}>"
+      end
+    end
+
+    # Per-phase durations of a request in seconds, from Response#timings. The
+    # native side provides dns, connect, tls, ttfb, download and total.
+    class Timings
+      PHASES = %i[dns connect tls ttfb download total].freeze
+
+      def to_h
+        PHASES.to_h { |phase| [phase, public_send(phase)] }
+      end
+
+      def inspect
+        phases = to_h.map { |phase, secs| "#{phase}=#{secs ? format('%.4f', secs) : 'nil'}" }
+        "#<Wreq::HTTP::Timings #{phases.join(' ')}>"
+      end
+    end
+
+    # A redirect followed on the way to a response, from Response#history. The
+    # native side provides status, uri, location, headers and elapsed.
+    class Redirect
+      alias raw_status status
+      def status
+        Status.new(raw_status)
+      end
+
+      def inspect
+        "#<Wreq::HTTP::Redirect #{raw_status} #{uri} -> #{location}>"
+      end
+    end
+
+    # Multipart form parts, compatible with http.rb's HTTP::FormData. A `form:`
+    # hash holding any of them is sent as multipart/form-data.
+    module FormData
+      # A part held in memory.
+      class Part
+        attr_reader :content_type, :filename
+
+        def initialize(body, content_type: nil, filename: nil)
+          @body = body.to_s
+          @content_type = content_type
+          @filename = filename
+        end
+
+        def to_s
+          @body
+        end
+      end
+
+      # A file part. Given a path, Pathname or File, the native side streams it
+      # from disk as the request is sent; any other IO is read into memory.
+      class File < Part
+        DEFAULT_MIME = 'application/octet-stream'
+
+        attr_reader :path
+
+        def initialize(path_or_io, content_type: DEFAULT_MIME, filename: nil)
+          if path_or_io.is_a?(String)
+            @path = path_or_io
+          else
+            @io = path_or_io
+            @path = path_or_io.to_path if path_or_io.respond_to?(:to_path)
+          end
+          @content_type = content_type
+          @filename = filename || (@path ? ::File.basename(@path) : "stream-#{@io.object_id}")
+        end
+
+        def to_s
+          return ::File.binread(@path) if @path
+
+          @io.rewind if @io.respond_to?(:rewind)
+          @io.read
+        end
+      end
+    end
+
     class Response
+      # Body of a `stream: true` response, read from the connection on demand.
+      # Matches http.rb's HTTP::Response::Body; the native side provides
+      # readpartial and to_s.
+      class Body
+        include Enumerable
+
+        def each
+          return to_enum(:each) unless block_given?
+
+          loop do
+            yield readpartial
+          rescue EOFError
+            break
+          end
+          self
+        end
+
+        alias to_str to_s
+
+        def inspect
+          "#<#{self.class.name}>"
+        end
+      end
+
       alias raw_status status
       def status
         Status.new(raw_status)
       end
 
+      # Writes the body to `path` and returns the number of bytes written. When
+      # `path` is a directory the file is named after Content-Disposition or the URL.
+      alias raw_save_to save_to
+      def save_to(path)
+        path = path.to_path if path.respond_to?(:to_path)
+        path = File.join(path, filename || 'download') if File.directory?(path)
+        raw_save_to(path.to_s)
+      end
+
+      # JSON bodies (application/json and any +json type) are parsed natively;
+      # anything else comes back as text.
       def parse
-        ct = content_type
-        if ct&.include?('application/json')
-          JSON.parse(body)
-        else
-          body
-        end
+        json_media_type? ? json : text
       end
 
+      # Reads any remaining streamed body so the connection can be reused.
       def flush
+        to_s if body.is_a?(Body)
         self
       end
 
-      def cookies
-        return {} unless headers
-
-        cookies_hash = {}
-        headers.each do |key, value|
-          next unless key.downcase == 'set-cookie'
+      def json_media_type?
+        mime_type = content_type.to_s.split(';').first.to_s.strip.downcase
+        mime_type == 'application/json' || mime_type.end_with?('+json')
+      end
 
+      def cookies
+        headers.get('set-cookie').each_with_object({}) do |value, cookies_hash|
           # Simple extraction: name=value
           cookies_hash[::Regexp.last_match(1)] = ::Regexp.last_match(2) if value =~ /^([^=]+)=([^;]+)/
         end
-        cookies_hash
+      end
+    end
+
+    class Client
+      # Streams `url` straight to disk. Returns the number of bytes written.
+      def download(url, to:, **options)
+        get(url, options.merge(stream: true)).save_to(to)
       end
     end
 
     class << self
       alias through via
 
+      def download(url, to:, **options)
+        Client.new.download(url, to: to, **options)
+      end
+
       # Override module-level persistent to add block support (yield + ensure close).
       # The Rust native extension defines a basic persistent that just returns a client.
       alias raw_persistent persistent
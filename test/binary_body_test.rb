@@ -0,0 +1,41 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'base64'
+require_relative '../lib/wreq_rb'
+
+class BinaryBodyTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  BYTES = "\x08\x96\x01\xFF\x00\xC3".b
+
+  def test_binary_body_is_sent_byte_for_byte
+    data = HTTP.post('https://httpbingo.org/post', body: BYTES).json
+
+    prefix = 'data:application/octet-stream;base64,'
+    assert data['data'].start_with?(prefix)
+    assert_equal BYTES, Base64.decode64(data['data'].delete_prefix(prefix))
+    assert_equal ['application/octet-stream'], data['headers']['Content-Type']
+  end
+
+  def test_caller_content_type_is_kept
+    data = HTTP.headers(content_type: 'application/x-protobuf')
+               .post('https://httpbingo.org/post', body: BYTES).json
+
+    assert_equal ['application/x-protobuf'], data['headers']['Content-Type']
+  end
+
+  def test_text_body_keeps_text_content_type
+    data = HTTP.post('https://httpbingo.org/post', body: 'plain text').json
+
+    assert_equal 'plain text', data['data']
+    assert_equal ['text/plain; charset=utf-8'], data['headers']['Content-Type']
+  end
+
+  def test_other_encodings_are_labelled
+    body = 'テキスト'.encode('Shift_JIS')
+    data = HTTP.post('https://httpbingo.org/post', body: body).json
+
+    assert_equal ['text/plain; charset=Shift_JIS'], data['headers']['Content-Type']
+  end
+end
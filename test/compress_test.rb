@@ -0,0 +1,64 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'base64'
+require 'stringio'
+require 'zlib'
+require_relative '../lib/wreq_rb'
+
+class CompressTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  PAYLOAD = { events: Array.new(200) { |i| { id: i, name: 'event' } } }.freeze
+
+  # httpbingo echoes binary request bodies as a base64 data URL
+  def sent_bytes(data)
+    Base64.decode64(data['data'].sub(/\Adata:[^,]*,/, ''))
+  end
+
+  def test_gzip_json_body
+    data = HTTP.post('https://httpbingo.org/post', json: PAYLOAD, compress: :gzip).json
+
+    assert_equal ['gzip'], data['headers']['Content-Encoding']
+    assert_equal JSON.generate(PAYLOAD), Zlib.gunzip(sent_bytes(data))
+  end
+
+  def test_client_level_compression
+    data = HTTP.compress(:gzip).post('https://httpbingo.org/post', body: 'a' * 10_000).json
+
+    assert_equal ['gzip'], data['headers']['Content-Encoding']
+    assert_operator sent_bytes(data).bytesize, :<, 10_000
+  end
+
+  def test_request_can_turn_client_compression_off
+    data = HTTP.compress(:gzip).post('https://httpbingo.org/post', body: 'plain', compress: false).json
+
+    assert_nil data['headers']['Content-Encoding']
+    assert_equal 'plain', data['data']
+  end
+
+  def test_zstd_and_brotli
+    %i[zstd br].each do |compression|
+      data = HTTP.post('https://httpbingo.org/post', form: { q: 'x' * 1000 }, compress: compression).json
+
+      assert_equal [compression.to_s], data['headers']['Content-Encoding']
+    end
+  end
+
+  def test_streamed_body_is_compressed
+    data = HTTP.post('https://httpbingo.org/post', body: StringIO.new('b' * 100_000), compress: :gzip).json
+
+    assert_equal 'b' * 100_000, Zlib.gunzip(sent_bytes(data))
+    assert_nil data['headers']['Content-Length']
+  end
+
+  def test_no_body_no_content_encoding
+    data = HTTP.compress(:gzip).get('https://httpbingo.org/get').json
+
+    assert_nil data['headers']['Content-Encoding']
+  end
+
+  def test_unknown_compression
+    assert_raises(ArgumentError) { HTTP.compress(:lz4) }
+  end
+end
@@ -0,0 +1,65 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'tmpdir'
+require 'json'
+require_relative '../lib/wreq_rb'
+
+class DownloadTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def setup
+    @dir = Dir.mktmpdir('wreq-download')
+  end
+
+  def teardown
+    FileUtils.remove_entry(@dir)
+  end
+
+  def test_download_writes_file_and_returns_bytes
+    path = File.join(@dir, 'bytes.bin')
+    written = HTTP.download('https://httpbingo.org/bytes/4096', to: path)
+
+    assert_equal 4096, written
+    assert_equal 4096, File.size(path)
+  end
+
+  def test_download_decompresses_on_the_fly
+    path = File.join(@dir, 'gzip.json')
+    written = HTTP.download('https://httpbin.org/gzip', to: path)
+
+    assert_equal File.size(path), written
+    assert_equal true, JSON.parse(File.read(path))['gzipped']
+  end
+
+  def test_save_to_buffered_response
+    response = HTTP.get('https://httpbingo.org/bytes/1024')
+    path = File.join(@dir, 'buffered.bin')
+
+    assert_equal 1024, response.save_to(path)
+    assert_equal response.body, File.binread(path)
+  end
+
+  def test_save_to_directory_uses_content_disposition
+    response = HTTP.get('https://httpbingo.org/response-headers?Content-Disposition=attachment%3B%20filename%3D%22report.txt%22',
+                        stream: true)
+    assert_equal 'report.txt', response.filename
+
+    response.save_to(@dir)
+    assert File.exist?(File.join(@dir, 'report.txt'))
+  end
+
+  def test_filename_falls_back_to_url
+    response = HTTP.get('https://httpbingo.org/image/png', stream: true)
+    assert_equal 'png', response.filename
+  end
+
+  def test_save_to_respects_limits
+    path = File.join(@dir, 'too-large.bin')
+
+    assert_raises(HTTP::BodyTooLargeError) do
+      HTTP.download('https://httpbingo.org/stream-bytes/10000?chunk_size=1000', to: path, max_body_size: 2000)
+    end
+    refute File.exist?(path), 'partial file should be removed'
+  end
+end
@@ -0,0 +1,109 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class EmulationTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_emulations_lists_every_profile
+    emulations = HTTP.emulations
+
+    assert_includes emulations, :chrome_134
+    assert_includes emulations, :firefox_135
+    assert_includes emulations, :'safari_ios_17.4.1'
+    assert_equal emulations.uniq, emulations
+  end
+
+  def test_emulate_sends_the_profiles_user_agent
+    chrome = HTTP.emulate(:chrome_134).get('https://httpbingo.org/user-agent').json
+    firefox = HTTP::Client.new(emulation: :firefox_135).get('https://httpbingo.org/user-agent').json
+
+    assert_match(%r{Chrome/134}, chrome['user-agent'])
+    assert_match(%r{Firefox/135}, firefox['user-agent'])
+  end
+
+  def test_names_with_dots_or_underscores
+    HTTP.emulate(:'safari_ios_17.4.1')
+    HTTP.emulate(:safari_ios_17_4_1)
+    HTTP.emulate('safari_17.0')
+  end
+
+  def test_every_listed_profile_builds
+    HTTP.emulations.each { |name| HTTP.emulate(name) }
+  end
+
+  def test_client_emulate_keeps_settings
+    client = HTTP.headers(x_test: 'kept').emulate(:chrome_134)
+    data = client.get('https://httpbingo.org/headers').json
+
+    assert_equal ['kept'], data['headers']['X-Test']
+  end
+
+  def test_os
+    windows = HTTP.emulate(:chrome_134, os: :windows).get('https://httpbingo.org/user-agent').json
+    linux = HTTP::Client.new(emulation: :chrome_134, os: :linux).get('https://httpbingo.org/user-agent').json
+
+    assert_match(/Windows/, windows['user-agent'])
+    assert_match(/Linux/, linux['user-agent'])
+  end
+
+  def test_skip_headers_keeps_only_ours
+    client = HTTP.emulate(:chrome_134, skip_headers: true).headers(user_agent: 'MyCrawler/1.0')
+    data = client.get('https://httpbingo.org/headers').json
+
+    assert_equal ['MyCrawler/1.0'], data['headers']['User-Agent']
+    refute data['headers'].key?('Sec-Ch-Ua')
+  end
+
+  def test_skip_http2
+    response = HTTP.emulate(:chrome_134, skip_http2: true).get('https://httpbingo.org/get')
+
+    assert_equal 200, response.status
+  end
+
+  def test_random_seed_replays_picks
+    HTTP.random_seed = 42
+    first = Array.new(3) { HTTP.desktop.get('https://httpbingo.org/user-agent').json['user-agent'] }
+    HTTP.random_seed = 42
+    second = Array.new(3) { HTTP.desktop.get('https://httpbingo.org/user-agent').json['user-agent'] }
+
+    assert_equal 42, HTTP.random_seed
+    assert_equal first, second
+  ensure
+    HTTP.random_seed = nil
+  end
+
+  def test_client_seed
+    agents = Array.new(2) do
+      HTTP::Client.new(seed: 7).get('https://httpbingo.org/user-agent').json['user-agent']
+    end
+
+    assert_equal 1, agents.uniq.size
+  end
+
+  def test_desktop_and_mobile_seed
+    desktop = HTTP.desktop(seed: 42).get('https://httpbingo.org/user-agent').json['user-agent']
+    mobile = HTTP::Client.new_mobile(seed: 42).get('https://httpbingo.org/user-agent').json['user-agent']
+
+    assert_match %r{Chrome/101}, desktop
+    assert_match(/iPhone OS 16_5/, mobile)
+    assert_equal desktop, HTTP::Client.new_desktop(seed: 42).get('https://httpbingo.org/user-agent').json['user-agent']
+    assert_raises(ArgumentError) { HTTP.mobile(sede: 42) }
+  end
+
+  def test_emulation_and_seed_conflict
+    assert_raises(ArgumentError) { HTTP::Client.new(emulation: :chrome_134, seed: 7) }
+  end
+
+  def test_unknown_emulation_options
+    assert_raises(ArgumentError) { HTTP.emulate(:chrome_134, os: :beos) }
+    assert_raises(ArgumentError) { HTTP.emulate(:chrome_134, platform: :windows) }
+  end
+
+  def test_unknown_emulation
+    assert_raises(ArgumentError) { HTTP.emulate(:chrome_1) }
+    assert_raises(ArgumentError) { HTTP::Client.new(emulation: :netscape_4) }
+    assert_raises(ArgumentError) { HTTP::Client.new(emulatoin: :chrome_134) }
+  end
+end
@@ -0,0 +1,83 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class ErrorTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_hierarchy
+    assert HTTP::Error < RuntimeError
+    assert HTTP::ConnectionError < HTTP::Error
+    assert HTTP::TLSError < HTTP::ConnectionError
+    assert HTTP::ProxyError < HTTP::ConnectionError
+    assert HTTP::TimeoutError < HTTP::Error
+    assert HTTP::RedirectError < HTTP::Error
+    assert HTTP::DecompressionError < HTTP::Error
+    assert HTTP::BodyReadError < HTTP::Error
+    assert HTTP::BodyTooLargeError < HTTP::BodyReadError
+    assert HTTP::InterruptError < HTTP::Error
+    assert HTTP::StateError < HTTP::Error
+  end
+
+  def test_timeout_raises_timeout_error
+    assert_raises(HTTP::TimeoutError) do
+      HTTP.timeout(0.5).get('https://httpbingo.org/delay/10')
+    end
+  end
+
+  def test_connection_refused_raises_connection_error
+    assert_raises(HTTP::ConnectionError) do
+      HTTP.get('http://127.0.0.1:1/')
+    end
+  end
+
+  def test_dns_failure_raises_connection_error
+    assert_raises(HTTP::ConnectionError) do
+      HTTP.get('https://does-not-exist.invalid/')
+    end
+  end
+
+  def test_bad_certificate_raises_tls_error
+    assert_raises(HTTP::TLSError) do
+      HTTP.get('https://expired.badssl.com/')
+    end
+  end
+
+  def test_too_many_redirects_raises_redirect_error
+    error = assert_raises(HTTP::RedirectError) do
+      HTTP.follow(max_hops: 1).get('https://httpbingo.org/redirect/3')
+    end
+
+    assert_equal 1, error.redirects
+  end
+
+  def test_errors_are_still_runtime_errors
+    assert_raises(RuntimeError) do
+      HTTP.get('http://127.0.0.1:1/')
+    end
+  end
+
+  def test_error_carries_request_context
+    error = assert_raises(HTTP::ConnectionError) do
+      HTTP.get('http://127.0.0.1:1/')
+    end
+
+    assert_equal 'http://127.0.0.1:1/', error.url
+    assert_equal 'GET', error.http_method
+    assert_kind_of Float, error.elapsed
+    assert_equal :connection_refused, error.cause_kind
+    assert_kind_of Integer, error.errno
+    assert_kind_of Array, error.source_chain
+    refute_empty error.source_chain
+  end
+
+  def test_timeout_error_context
+    error = assert_raises(HTTP::TimeoutError) do
+      HTTP.timeout(0.5).post('https://httpbingo.org/delay/10', body: 'x')
+    end
+
+    assert_equal 'POST', error.http_method
+    assert error.elapsed >= 0.5
+  end
+end
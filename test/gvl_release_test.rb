@@ -2,6 +2,7 @@
 
 require 'minitest/autorun'
 require_relative '../lib/wreq_rb'
+require 'timeout'
 
 class GVLReleaseTest < Minitest::Test
   HTTP = Wreq::HTTP
@@ -22,6 +23,65 @@ class GVLReleaseTest < Minitest::Test
     assert_equal 200, response.status
   end
 
+  def test_gvl_released_while_request_body_is_compressed
+    body = Random.new(1).bytes(4 * 1024 * 1024).unpack1('H*')
+    max_gap = 0.0
+    running = true
+    ticker = Thread.new do
+      last = monotonic_now
+      while running
+        now = monotonic_now
+        max_gap = [max_gap, now - last].max
+        last = now
+      end
+    end
+    sleep 0.05
+
+    started = monotonic_now
+    # Nothing listens on the discard port: the request fails right after compressing
+    assert_raises(HTTP::ConnectionError) { HTTP.post('http://127.0.0.1:9/', body: body, compress: :br) }
+    elapsed = monotonic_now - started
+    running = false
+    ticker.join
+
+    # Held GVL would stall the ticker for the whole compression
+    assert_operator max_gap, :<, elapsed / 2
+  end
+
+  def test_timeout_interrupts_in_flight_request
+    started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+    assert_raises(Timeout::Error) do
+      Timeout.timeout(1) { HTTP.get('https://httpbingo.org/delay/10') }
+    end
+    elapsed = Process.clock_gettime(Process::CLOCK_MONOTONIC) - started
+
+    assert elapsed < 5, "Timeout.timeout should cancel the request, took #{elapsed}s"
+  end
+
+  def test_thread_raise_interrupts_in_flight_request
+    thread = Thread.new { HTTP.get('https://httpbingo.org/delay/10') }
+    sleep 0.5
+    thread.raise(ArgumentError, 'stop')
+
+    error = assert_raises(ArgumentError) { thread.join(5) }
+    assert_equal 'stop', error.message
+  end
+
+  def test_thread_kill_stops_in_flight_request
+    thread = Thread.new { HTTP.get('https://httpbingo.org/delay/10') }
+    sleep 0.5
+    thread.kill
+
+    refute_nil thread.join(5), 'Thread#kill should stop the request within 5 seconds'
+    refute thread.alive?
+  end
+
+  private
+
+  def monotonic_now
+    Process.clock_gettime(Process::CLOCK_MONOTONIC)
+  end
+
   # NOTE: WREQ_RB_NO_GVL_RELEASE env var bypass test is skipped because:
   # - Rust's std::env::var() reads process environment at startup
   # - Ruby's ENV['X'] = 'Y' does NOT propagate to Rust's std::env
@@ -0,0 +1,57 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class HeadersTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_lookup_is_case_insensitive
+    response = HTTP.get('https://httpbingo.org/get')
+    headers = response.headers
+
+    assert_instance_of HTTP::Headers, headers
+    assert_equal headers['content-type'], headers['Content-Type']
+    assert_equal headers['content-type'], headers['CONTENT-TYPE']
+    assert headers.include?('Content-Type')
+    assert_nil headers['X-Does-Not-Exist']
+  end
+
+  def test_repeated_headers_keep_every_value
+    response = HTTP.get('https://httpbingo.org/response-headers?X-Multi=one&X-Multi=two')
+    headers = response.headers
+
+    assert_equal %w[one two], headers.get('x-multi')
+    assert_equal %w[one two], headers['X-Multi']
+    assert_equal [%w[x-multi one], %w[x-multi two]], headers.select { |name, _| name == 'x-multi' }
+  end
+
+  def test_get_returns_empty_array_when_missing
+    response = HTTP.get('https://httpbingo.org/get')
+    assert_equal [], response.headers.get('x-does-not-exist')
+  end
+
+  def test_each_yields_name_value_pairs
+    response = HTTP.get('https://httpbingo.org/get')
+    pairs = response.headers.each.to_a
+
+    refute_empty pairs
+    assert(pairs.all? { |name, value| name.is_a?(String) && value.is_a?(String) })
+    assert_equal response.headers.to_a, pairs
+  end
+
+  def test_to_h
+    response = HTTP.get('https://httpbingo.org/response-headers?X-Multi=one&X-Multi=two')
+    hash = response.headers.to_h
+
+    assert_instance_of Hash, hash
+    assert_equal %w[one two], hash['x-multi']
+  end
+
+  def test_multiple_cookies
+    response = HTTP.follow(false).get('https://httpbingo.org/cookies/set?first=1&second=2')
+
+    assert_equal 2, response.headers.get('set-cookie').size
+    assert_equal({ 'first' => '1', 'second' => '2' }, response.cookies)
+  end
+end
@@ -0,0 +1,57 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class HistoryTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_no_redirects
+    response = HTTP.get('https://httpbingo.org/get')
+
+    refute response.redirected?
+    assert_empty response.history
+  end
+
+  def test_redirect_chain
+    response = HTTP.get('https://httpbingo.org/redirect/3')
+
+    assert_equal 200, response.status
+    assert response.redirected?
+    assert_equal 3, response.history.size
+    assert_equal 'https://httpbingo.org/redirect/3', response.history.first.uri
+    assert_equal 'https://httpbingo.org/get', response.uri
+  end
+
+  def test_hops_link_up
+    history = HTTP.get('https://httpbingo.org/redirect/3').history
+
+    history.each_cons(2) do |hop, next_hop|
+      assert_equal hop.location, next_hop.uri
+    end
+  end
+
+  def test_hop_details
+    hop = HTTP.get('https://httpbingo.org/redirect-to?url=%2Fget&status_code=307').history.first
+
+    assert_equal 307, hop.status
+    assert hop.status.redirect?
+    assert_equal '/get', hop.headers['Location']
+    assert_equal 'https://httpbingo.org/get', hop.location
+    assert_operator hop.elapsed, :>, 0
+  end
+
+  def test_not_followed
+    response = HTTP.follow(false).get('https://httpbingo.org/redirect/1')
+
+    assert_equal 302, response.status
+    refute response.redirected?
+    assert_empty response.history
+  end
+
+  def test_max_hops_still_enforced
+    assert_raises(HTTP::RedirectError) do
+      HTTP.follow(max_hops: 2).get('https://httpbingo.org/redirect/5')
+    end
+  end
+end
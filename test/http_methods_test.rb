@@ -0,0 +1,41 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class HttpMethodsTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_extension_methods
+    %i[propfind report purge].each do |verb|
+      data = HTTP.request(verb, 'https://httpbingo.org/anything').json
+
+      assert_equal verb.to_s.upcase, data['method']
+    end
+  end
+
+  def test_string_verbs
+    data = HTTP.request('PROPFIND', 'https://httpbingo.org/anything').json
+
+    assert_equal 'PROPFIND', data['method']
+  end
+
+  def test_query_method_with_body
+    data = HTTP.request(:query, 'https://httpbingo.org/anything', json: { select: 'name' }).json
+
+    assert_equal 'QUERY', data['method']
+    assert_equal({ 'select' => 'name' }, data['json'])
+  end
+
+  def test_top_level_options
+    response = HTTP.options('https://httpbingo.org/anything')
+
+    assert_operator response.status, :<, 500
+    assert_equal 200, HTTP::Client.new.options('https://httpbingo.org/anything').status
+  end
+
+  def test_invalid_verb
+    assert_raises(ArgumentError) { HTTP.request('BAD VERB', 'https://httpbingo.org/anything') }
+    assert_raises(ArgumentError) { HTTP.request(:"", 'https://httpbingo.org/anything') }
+  end
+end
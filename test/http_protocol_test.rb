@@ -17,6 +17,7 @@ class HttpProtocolTest < Minitest::Test
     # Verify HTTP/1.1 connections work and return valid responses
     response = HTTP.get('https://http1.golang.org/')
     assert_equal 200, response.status.to_i
+    assert_equal '1.1', response.version
     assert_kind_of String, response.body
     assert response.body.length > 0
   end
@@ -25,6 +26,7 @@ class HttpProtocolTest < Minitest::Test
     # Verify HTTP/2 connections work and return valid responses
     response = HTTP.get('https://http2.golang.org/')
     assert_equal 200, response.status.to_i
+    assert_equal '2', response.version
     assert_kind_of String, response.body
     assert response.body.length > 0
   end
@@ -33,10 +35,37 @@ class HttpProtocolTest < Minitest::Test
     # httpbingo.org supports HTTP/2; verify we can connect
     response = HTTP.get('https://httpbingo.org/get')
     assert_equal 200, response.status.to_i
+    assert_equal '2', response.version
     body = JSON.parse(response.body)
     assert_kind_of Hash, body
   end
 
+  # ============================================================================
+  # Connection Metadata Tests
+  # ============================================================================
+
+  def test_remote_and_local_addr
+    response = HTTP.get('https://httpbingo.org/get')
+    assert_match(/:443\z/, response.remote_addr)
+    refute_nil response.local_addr
+    refute_equal response.remote_addr, response.local_addr
+  end
+
+  def test_connection_seen_before_within_client
+    client = HTTP::Client.new
+    first = client.get('https://httpbingo.org/get')
+    second = client.get('https://httpbingo.org/get')
+
+    refute first.connection_seen_before?
+    assert second.connection_seen_before?
+    assert_equal first.local_addr, second.local_addr
+  end
+
+  def test_separate_clients_do_not_share_connections
+    refute HTTP.get('https://httpbingo.org/get').connection_seen_before?
+    refute HTTP.get('https://httpbingo.org/get').connection_seen_before?
+  end
+
   # ============================================================================
   # Concurrent Request Tests
   # ============================================================================
@@ -0,0 +1,133 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'base64'
+require_relative '../lib/wreq_rb'
+
+class JsonTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_json_matches_json_parse
+    response = HTTP.get('https://httpbingo.org/json')
+
+    assert_equal JSON.parse(response.to_s), response.json
+  end
+
+  def test_symbolize_names
+    data = HTTP.get('https://httpbingo.org/json').json(symbolize_names: true)
+
+    assert_kind_of Hash, data[:slideshow]
+    assert_kind_of String, data[:slideshow][:title]
+  end
+
+  def test_scalar_types
+    data = HTTP.get('https://httpbingo.org/get?n=1').json
+
+    assert_kind_of Hash, data['args']
+    assert_equal ['1'], data['args']['n']
+  end
+
+  def test_big_numbers_match_json_parse
+    doc = '{"big":123456789012345678901234567890,"neg":-18446744073709551616,"max":18446744073709551615,' \
+          '"float":1.5e300,"tiny":0.1000000000000000055511151231257827}'
+    data = HTTP.get("https://httpbingo.org/base64/#{Base64.urlsafe_encode64(doc)}").json
+
+    assert_equal 123_456_789_012_345_678_901_234_567_890, data['big']
+    assert_equal(-2**64, data['neg'])
+    assert_equal JSON.parse(doc), data
+  end
+
+  def test_parse_uses_json_for_json_media_types
+    assert_kind_of Hash, HTTP.get('https://httpbingo.org/json').parse
+
+    # application/problem+json style types
+    response = HTTP.get('https://httpbingo.org/response-headers?Content-Type=application%2Fvnd.api%2Bjson')
+    assert_kind_of Hash, response.parse
+  end
+
+  def test_parse_returns_text_otherwise
+    assert_kind_of String, HTTP.get('https://httpbingo.org/html').parse
+  end
+
+  def test_invalid_json_raises_parser_error
+    assert_raises(JSON::ParserError) { HTTP.get('https://httpbingo.org/html').json }
+  end
+
+  def test_max_nesting
+    response = HTTP.get('https://httpbingo.org/json')
+
+    assert_raises(JSON::NestingError) { response.json(max_nesting: 1) }
+    assert_kind_of Hash, response.json(max_nesting: false)
+  end
+
+  def test_unknown_keyword
+    response = HTTP.get('https://httpbingo.org/json')
+
+    assert_raises(ArgumentError) { response.json(symbolize_keys: true) }
+  end
+
+  def test_streamed_body
+    response = HTTP.get('https://httpbingo.org/json', stream: true)
+
+    assert_kind_of Hash, response.json
+  end
+
+  def test_request_body_types
+    payload = { name: 'Alice', tags: [:a, 'b'], n: 1, big: 2**70, ratio: 0.5, none: nil, ok: true,
+                nested: { 'deep' => [false, { x: 1 }] } }
+    data = HTTP.post('https://httpbingo.org/post', json: payload).json
+
+    assert_equal JSON.parse(JSON.generate(payload)), data['json']
+  end
+
+  def test_request_body_is_not_evaluated
+    text = %q{"); `touch /tmp/wreq_json_injection`; #{1} \u0000}
+    data = HTTP.post('https://httpbingo.org/post', json: { text: text }).json
+
+    assert_equal text, data['json']['text']
+    refute File.exist?('/tmp/wreq_json_injection')
+  end
+
+  def test_request_body_uses_as_json_and_to_json
+    point = Struct.new(:x, :y) do
+      def as_json(*)
+        { 'x' => x, 'y' => y }
+      end
+    end
+    raw = Class.new do
+      def to_json(*)
+        '{"raw":true}'
+      end
+    end
+    data = HTTP.post('https://httpbingo.org/post', json: { point: point.new(1, 2), raw: raw.new }).json
+
+    assert_equal({ 'point' => { 'x' => 1, 'y' => 2 }, 'raw' => { 'raw' => true } }, data['json'])
+  end
+
+  def test_request_body_prefers_own_to_json_over_as_json
+    both = Class.new do
+      def as_json(*)
+        { 'via' => 'as_json' }
+      end
+
+      def to_json(*)
+        '{"via":"to_json"}'
+      end
+    end
+    data = HTTP.post('https://httpbingo.org/post', json: { both: both.new }).json
+
+    assert_equal({ 'both' => { 'via' => 'to_json' } }, data['json'])
+    assert_equal JSON.parse(JSON.generate(both: both.new)), data['json']
+  end
+
+  def test_request_body_rejects_nan
+    assert_raises(JSON::GeneratorError) { HTTP.post('https://httpbingo.org/post', json: { n: Float::NAN }) }
+  end
+
+  def test_request_body_nesting_limit
+    deep = []
+    150.times { deep = [deep] }
+
+    assert_raises(JSON::NestingError) { HTTP.post('https://httpbingo.org/post', json: deep) }
+  end
+end
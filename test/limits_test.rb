@@ -0,0 +1,64 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+require 'json'
+
+class LimitsTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_max_body_size_per_request
+    assert_raises(HTTP::BodyTooLargeError) do
+      HTTP.get('https://httpbingo.org/bytes/10000', max_body_size: 1000)
+    end
+  end
+
+  def test_max_body_size_per_client
+    client = HTTP.limits(max_body_size: 1000)
+
+    assert_raises(HTTP::BodyTooLargeError) { client.get('https://httpbingo.org/bytes/10000') }
+    assert_equal 500, client.get('https://httpbingo.org/bytes/500').body.bytesize
+  end
+
+  def test_request_overrides_client_limit
+    client = HTTP.limits(max_body_size: 100)
+    response = client.get('https://httpbingo.org/bytes/500', max_body_size: 1000)
+    assert_equal 500, response.body.bytesize
+  end
+
+  def test_max_body_size_without_content_length
+    assert_raises(HTTP::BodyTooLargeError) do
+      HTTP.get('https://httpbingo.org/stream-bytes/10000?chunk_size=1000', max_body_size: 2000)
+    end
+  end
+
+  def test_max_decompressed_size
+    error = assert_raises(HTTP::BodyTooLargeError) do
+      HTTP.get('https://httpbin.org/gzip', max_decompressed_size: 10)
+    end
+    assert_match(/max_decompressed_size/, error.message)
+  end
+
+  def test_max_compression_ratio
+    error = assert_raises(HTTP::BodyTooLargeError) do
+      HTTP.get('https://httpbin.org/gzip', max_compression_ratio: 1)
+    end
+    assert_match(/max_compression_ratio/, error.message)
+  end
+
+  def test_limits_apply_to_streamed_bodies
+    response = HTTP.get('https://httpbingo.org/stream-bytes/10000?chunk_size=1000', stream: true, max_body_size: 2000)
+
+    assert_raises(HTTP::BodyTooLargeError) { response.body.each { |_chunk| nil } }
+  end
+
+  def test_too_large_is_a_body_read_error
+    assert_raises(HTTP::BodyReadError) do
+      HTTP.get('https://httpbingo.org/bytes/10000', max_body_size: 1000)
+    end
+  end
+
+  def test_invalid_ratio_raises_argument_error
+    assert_raises(ArgumentError) { HTTP.limits(max_compression_ratio: 0.5) }
+  end
+end
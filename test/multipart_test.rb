@@ -0,0 +1,92 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'stringio'
+require 'tempfile'
+require_relative '../lib/wreq_rb'
+
+class MultipartTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def setup
+    @file = Tempfile.new(['upload', '.txt'])
+    @file.write('file contents')
+    @file.flush
+  end
+
+  def teardown
+    @file.close!
+  end
+
+  def test_file_upload_from_path
+    file = HTTP::FormData::File.new(@file.path, content_type: 'text/plain')
+    response = HTTP.post('https://httpbingo.org/post', form: { name: 'Alice', upload: file })
+    data = response.json
+
+    assert_equal ['Alice'], data['form']['name']
+    assert_equal ['file contents'], data['files']['upload']
+    assert_match %r{\Amultipart/form-data; boundary=}, data['headers']['Content-Type'].first
+  end
+
+  def test_content_length_is_sent
+    file = HTTP::FormData::File.new(@file.path)
+    data = HTTP.post('https://httpbingo.org/post', form: { upload: file }).json
+
+    refute_nil data['headers']['Content-Length']
+    assert_nil data['headers']['Transfer-Encoding']
+  end
+
+  def test_file_upload_from_io
+    file = HTTP::FormData::File.new(StringIO.new('from io'), filename: 'io.txt')
+    data = HTTP.post('https://httpbingo.org/post', form: { upload: file }).json
+
+    assert_equal ['from io'], data['files']['upload']
+  end
+
+  def test_in_memory_part_and_arrays
+    part = HTTP::FormData::Part.new('{"a":1}', content_type: 'application/json', filename: 'a.json')
+    data = HTTP.post('https://httpbingo.org/post', form: { parts: [part, part], page: 2 }).json
+
+    assert_equal 2, data['files']['parts[]'].size
+    assert_equal ['2'], data['form']['page']
+  end
+
+  def test_nested_values_next_to_a_file
+    file = HTTP::FormData::File.new(@file.path)
+    form = { user: { name: 'Alice', roles: %w[admin dev] }, tags: %w[a b], draft: nil, upload: file }
+    data = HTTP.post('https://httpbingo.org/post', form: form).json
+
+    assert_equal ['Alice'], data['form']['user[name]']
+    assert_equal %w[admin dev], data['form']['user[roles][]']
+    assert_equal %w[a b], data['form']['tags[]']
+    assert_equal [''], data['form']['draft']
+    assert_equal ['file contents'], data['files']['upload']
+  end
+
+  def test_array_style_applies_to_parts
+    file = HTTP::FormData::File.new(@file.path)
+    data = HTTP.post('https://httpbingo.org/post', form: { ids: [1, 2], upload: file }, array_style: :indexed).json
+
+    assert_equal ['1'], data['form']['ids[0]']
+    assert_equal ['2'], data['form']['ids[1]']
+  end
+
+  def test_content_type_with_line_break_is_rejected
+    part = HTTP::FormData::Part.new('x', content_type: "text/plain\r\nX-Injected: 1", filename: 'a.txt')
+
+    assert_raises(ArgumentError) { HTTP.post('https://httpbingo.org/post', form: { upload: part }) }
+  end
+
+  def test_filename_defaults_to_basename
+    file = HTTP::FormData::File.new(@file.path)
+
+    assert_equal File.basename(@file.path), file.filename
+    assert_equal 'application/octet-stream', file.content_type
+  end
+
+  def test_missing_file_raises_before_sending
+    file = HTTP::FormData::File.new('/nonexistent/upload.txt')
+
+    assert_raises(IOError, Errno::ENOENT) { HTTP.post('https://httpbingo.org/post', form: { upload: file }) }
+  end
+end
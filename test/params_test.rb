@@ -0,0 +1,63 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class ParamsTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_typed_values
+    args = HTTP.get('https://httpbingo.org/get', params: { page: 2, ratio: 0.5, active: true, q: :ruby }).json['args']
+
+    assert_equal({ 'page' => ['2'], 'ratio' => ['0.5'], 'active' => ['true'], 'q' => ['ruby'] }, args)
+  end
+
+  def test_nil_is_a_bare_key
+    response = HTTP.get('https://httpbingo.org/get', params: { flag: nil, page: 1 })
+
+    assert_equal 'https://httpbingo.org/get?flag&page=1', response.uri.to_s
+  end
+
+  def test_arrays_default_to_brackets
+    args = HTTP.get('https://httpbingo.org/get', params: { ids: [1, 2] }).json['args']
+
+    assert_equal({ 'ids[]' => %w[1 2] }, args)
+  end
+
+  def test_array_styles
+    repeat = HTTP.get('https://httpbingo.org/get', params: { ids: [1, 2] }, array_style: :repeat).json['args']
+    indexed = HTTP.get('https://httpbingo.org/get', params: { ids: [1, 2] }, array_style: :indexed).json['args']
+
+    assert_equal({ 'ids' => %w[1 2] }, repeat)
+    assert_equal({ 'ids[0]' => ['1'], 'ids[1]' => ['2'] }, indexed)
+  end
+
+  def test_unknown_array_style
+    assert_raises(ArgumentError) do
+      HTTP.get('https://httpbingo.org/get', params: { ids: [1] }, array_style: :commas)
+    end
+  end
+
+  def test_nested_hashes
+    args = HTTP.get('https://httpbingo.org/get', params: { filter: { status: 'open', tags: %w[a b] } }).json['args']
+
+    assert_equal({ 'filter[status]' => ['open'], 'filter[tags][]' => %w[a b] }, args)
+  end
+
+  def test_existing_query_is_kept_first
+    response = HTTP.get('https://httpbingo.org/get?q=rust&page=1', params: { page: 2 })
+
+    assert_equal 'https://httpbingo.org/get?q=rust&page=1&page=2', response.uri.to_s
+  end
+
+  def test_typed_and_nested_form
+    form = HTTP.post('https://httpbingo.org/post', form: { page: 2, user: { name: 'Alice', roles: %w[admin dev] } })
+               .json['form']
+
+    assert_equal({ 'page' => ['2'], 'user[name]' => ['Alice'], 'user[roles][]' => %w[admin dev] }, form)
+  end
+
+  def test_unconvertible_keys_raise
+    assert_raises(TypeError) { HTTP.get('https://httpbingo.org/get', params: { 1 => 'x' }) }
+  end
+end
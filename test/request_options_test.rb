@@ -0,0 +1,54 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class RequestOptionsTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_headers_are_merged_over_client_headers
+    client = HTTP.headers(x_client: 'kept', x_shared: 'client')
+    data = client.get('https://httpbingo.org/headers', headers: { x_shared: 'request' }).json
+
+    assert_equal ['kept'], data['headers']['X-Client']
+    assert_equal ['request'], data['headers']['X-Shared']
+  end
+
+  def test_overrides_apply_to_one_call_only
+    client = HTTP.headers(x_shared: 'client')
+    client.get('https://httpbingo.org/headers', headers: { x_shared: 'request' })
+    data = client.get('https://httpbingo.org/headers').json
+
+    assert_equal ['client'], data['headers']['X-Shared']
+  end
+
+  def test_auth
+    data = HTTP.get('https://httpbingo.org/headers', auth: 'Bearer secret').json
+
+    assert_equal ['Bearer secret'], data['headers']['Authorization']
+  end
+
+  def test_follow
+    response = HTTP.get('https://httpbingo.org/redirect/1', follow: false)
+
+    assert_equal 302, response.status
+    assert_equal 200, HTTP.follow(false).get('https://httpbingo.org/redirect/1', follow: true).status
+  end
+
+  def test_timeout
+    assert_raises(HTTP::TimeoutError) do
+      HTTP.get('https://httpbingo.org/delay/3', timeout: 0.5)
+    end
+  end
+
+  def test_invalid_proxy
+    assert_raises(ArgumentError) { HTTP.get('https://httpbingo.org/get', proxy: 'not a url') }
+  end
+
+  def test_unknown_keys
+    error = assert_raises(ArgumentError) { HTTP.get('https://httpbingo.org/get', timout: 5) }
+    assert_equal 'unknown keyword: :timout', error.message
+
+    assert_raises(ArgumentError) { HTTP.post('https://httpbingo.org/post', 'json' => { a: 1 }) }
+  end
+end
@@ -0,0 +1,50 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+require 'json'
+
+class ResponseBodyTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_binary_body_is_returned_unchanged
+    response = HTTP.get('https://httpbingo.org/image/png')
+    assert_equal 200, response.status
+
+    assert_equal Encoding::ASCII_8BIT, response.body.encoding
+    assert response.body.start_with?("\x89PNG\r\n\x1A\n".b), 'PNG signature should survive'
+  end
+
+  def test_random_bytes_match_content_length
+    response = HTTP.get('https://httpbingo.org/bytes/1024')
+    assert_equal 200, response.status
+
+    assert_equal 1024, response.body.bytesize
+    assert_equal response.content_length, response.body.bytesize
+  end
+
+  def test_to_s_matches_body
+    response = HTTP.get('https://httpbingo.org/bytes/64')
+    assert_equal response.body, response.to_s
+    assert_equal Encoding::ASCII_8BIT, response.to_s.encoding
+  end
+
+  def test_text_decodes_as_utf8
+    response = HTTP.get('https://httpbingo.org/encoding/utf8')
+    assert_equal 200, response.status
+
+    text = response.text
+    assert_equal Encoding::UTF_8, text.encoding
+    assert text.valid_encoding?
+    assert_equal response.body.bytesize, text.bytesize
+  end
+
+  def test_encoding_overrides_response_charset
+    # "caf\xE9" in ISO-8859-1
+    response = HTTP.encoding('ISO-8859-1').get('https://httpbingo.org/base64/Y2Fm6Q==')
+    assert_equal 200, response.status
+
+    assert_equal "caf\xE9".b, response.body
+    assert_equal 'café', response.text
+  end
+end
@@ -0,0 +1,85 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+require 'json'
+
+class StreamingTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_stream_returns_body_object
+    response = HTTP.get('https://httpbingo.org/bytes/1024', stream: true)
+    assert_equal 200, response.status
+    assert_instance_of HTTP::Response::Body, response.body
+  end
+
+  def test_each_yields_all_chunks
+    response = HTTP.get('https://httpbingo.org/stream-bytes/65536?chunk_size=4096', stream: true)
+
+    chunks = []
+    response.body.each { |chunk| chunks << chunk }
+
+    refute_empty chunks
+    assert(chunks.all? { |chunk| chunk.encoding == Encoding::ASCII_8BIT })
+    assert_equal 65_536, chunks.sum(&:bytesize)
+  end
+
+  def test_content_length_comes_from_the_header
+    response = HTTP.get('https://httpbingo.org/bytes/1000', stream: true)
+    assert_equal 1000, response.content_length
+  end
+
+  def test_content_length_is_nil_for_chunked_body
+    response = HTTP.get('https://httpbingo.org/stream-bytes/65536?chunk_size=4096', stream: true)
+    assert_nil response.content_length
+    response.body.to_s
+    assert_nil response.content_length
+  end
+
+  def test_readpartial_respects_size_and_raises_eof
+    response = HTTP.get('https://httpbingo.org/bytes/1000', stream: true)
+
+    total = 0
+    loop do
+      chunk = response.body.readpartial(100)
+      assert chunk.bytesize <= 100
+      total += chunk.bytesize
+    rescue EOFError
+      break
+    end
+
+    assert_equal 1000, total
+  end
+
+  def test_to_s_reads_whole_body
+    response = HTTP.get('https://httpbingo.org/get', stream: true)
+
+    data = JSON.parse(response.to_s)
+    assert_equal 'https://httpbingo.org/get', data['url']
+    assert_equal response.to_s, response.body.to_s
+  end
+
+  def test_parse_works_with_streamed_json
+    response = HTTP.get('https://httpbingo.org/json', stream: true)
+    assert_kind_of Hash, response.parse
+  end
+
+  def test_streamed_gzip_body_is_decompressed
+    response = HTTP.get('https://httpbingo.org/gzip', stream: true)
+    data = JSON.parse(response.body.each.to_a.join)
+    assert_equal true, data['gzipped']
+  end
+
+  def test_streamed_brotli_body_is_decompressed
+    response = HTTP.get('https://httpbin.org/brotli', stream: true)
+    data = JSON.parse(response.body.each.to_a.join)
+    assert_equal true, data['brotli']
+  end
+
+  def test_to_s_after_readpartial_raises_state_error
+    response = HTTP.get('https://httpbingo.org/bytes/1000', stream: true)
+    response.body.readpartial(10)
+
+    assert_raises(HTTP::StateError) { response.body.to_s }
+  end
+end
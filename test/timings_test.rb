@@ -0,0 +1,77 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require_relative '../lib/wreq_rb'
+
+class TimingsTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  # Session resumption is what lets the TLS handshake be timed on its own
+  def resumable
+    HTTP.emulate(:chrome_134)
+  end
+
+  def test_phases_of_a_fresh_https_request
+    timings = resumable.get('https://httpbingo.org/get').timings
+
+    assert_kind_of HTTP::Timings, timings
+    assert_operator timings.dns, :>, 0
+    assert_operator timings.connect, :>, 0
+    assert_operator timings.tls, :>, 0
+    assert_operator timings.ttfb, :>, 0
+    assert_operator timings.download, :>=, 0
+  end
+
+  def test_phases_add_up_to_total
+    timings = resumable.get('https://httpbingo.org/get').timings
+    sum = timings.dns + timings.connect + timings.tls + timings.ttfb + timings.download
+
+    assert_in_delta timings.total, sum, 0.001
+  end
+
+  def test_slow_upstream_shows_up_in_ttfb
+    timings = HTTP.get('https://httpbingo.org/delay/1').timings
+
+    assert_operator timings.ttfb, :>=, 1.0
+    assert_operator timings.connect, :<, 1.0
+  end
+
+  def test_tls_is_nil_without_session_resumption
+    timings = HTTP.emulate(:safari_17_0).get('https://httpbingo.org/get').timings
+
+    assert_nil timings.tls
+    assert_operator timings.connect, :>, 0
+  end
+
+  def test_tls_is_nil_over_plain_http
+    assert_nil resumable.get('http://httpbingo.org/get').timings.tls
+  end
+
+  def test_reused_connection_skips_setup
+    client = resumable
+    client.get('https://httpbingo.org/get')
+    response = client.get('https://httpbingo.org/get')
+
+    assert response.connection_seen_before?
+    assert_equal 0.0, response.timings.dns
+    assert_equal 0.0, response.timings.connect
+    assert_equal 0.0, response.timings.tls
+  end
+
+  def test_streamed_download_is_nil_until_read
+    response = HTTP.get('https://httpbingo.org/stream-bytes/4096', stream: true)
+    assert_nil response.timings.download
+    assert_nil response.timings.total
+
+    response.to_s
+    refute_nil response.timings.download
+    assert_operator response.timings.total, :>=, response.timings.ttfb
+  end
+
+  def test_to_h
+    hash = resumable.get('https://httpbingo.org/get').timings.to_h
+
+    assert_equal %i[dns connect tls ttfb download total], hash.keys
+    assert(hash.values.all? { |secs| secs.is_a?(Float) })
+  end
+end
@@ -0,0 +1,73 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'pathname'
+require 'stringio'
+require 'tempfile'
+require_relative '../lib/wreq_rb'
+
+class UploadTest < Minitest::Test
+  HTTP = Wreq::HTTP
+
+  def test_file_body_is_streamed_with_content_length
+    Tempfile.create('upload') do |file|
+      file.write('x' * 200_000)
+      file.rewind
+
+      data = HTTP.post('https://httpbingo.org/post', body: file).json
+
+      assert_equal 200_000, data['data'].bytesize
+      assert_equal ['200000'], data['headers']['Content-Length']
+    end
+  end
+
+  def test_stringio_body
+    data = HTTP.put('https://httpbingo.org/put', body: StringIO.new('hello from io')).json
+
+    assert_equal 'hello from io', data['data']
+  end
+
+  def test_enumerator_body_is_chunked
+    chunks = %w[hello , world].each
+    data = HTTP.post('https://httpbingo.org/post', body: chunks).json
+
+    assert_equal 'hello,world', data['data']
+    assert_nil data['headers']['Content-Length']
+  end
+
+  def test_pipe_body
+    reader, writer = IO.pipe
+    producer = Thread.new do
+      3.times { |i| writer.write("part#{i};") }
+      writer.close
+    end
+
+    data = HTTP.post('https://httpbingo.org/post', body: reader).json
+    producer.join
+
+    assert_equal 'part0;part1;part2;', data['data']
+  ensure
+    reader&.close
+  end
+
+  def test_pathname_body_is_rejected
+    Tempfile.create('upload') do |file|
+      error = assert_raises(ArgumentError) { HTTP.post('https://httpbingo.org/post', body: Pathname(file.path)) }
+      assert_match(/File\.open/, error.message)
+    end
+  end
+
+  def test_hash_body_is_rejected
+    assert_raises(ArgumentError) { HTTP.post('https://httpbingo.org/post', body: { a: 1 }) }
+  end
+
+  def test_errors_from_the_body_are_raised
+    failing = Enumerator.new do |y|
+      y << 'first'
+      raise ArgumentError, 'boom'
+    end
+
+    error = assert_raises(ArgumentError) { HTTP.post('https://httpbingo.org/post', body: failing) }
+    assert_equal 'boom', error.message
+  end
+end